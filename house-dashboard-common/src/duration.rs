@@ -17,7 +17,7 @@
 use regex::Regex;
 
 /// A duration
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Iso8601Duration {
     /// Duration
     pub duration: Duration,
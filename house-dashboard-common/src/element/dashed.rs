@@ -0,0 +1,114 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for generating dashed paths
+
+use plotters::element::{Drawable, PointCollection};
+use plotters::style::ShapeStyle;
+
+use plotters_backend::{BackendCoord, DrawingBackend, DrawingErrorKind};
+
+/// Dashed path
+///
+/// Dashes continue across the vertices of the path, so that the pattern is
+/// uniform along the whole path.
+pub struct DashedPath<Coord> {
+    /// Points
+    points: Vec<Coord>,
+
+    /// Dash length in pixels
+    dash: f64,
+
+    /// Gap length in pixels
+    gap: f64,
+
+    /// Style
+    style: ShapeStyle,
+}
+
+impl<Coord> DashedPath<Coord> {
+    /// Create a new dashed path
+    #[must_use]
+    pub fn new<P, S>(points: P, dash: u32, gap: u32, style: S) -> Self
+    where
+        P: Into<Vec<Coord>>,
+        S: Into<ShapeStyle>,
+    {
+        Self {
+            points: points.into(),
+            dash: f64::from(dash.max(1)),
+            gap: f64::from(gap),
+            style: style.into(),
+        }
+    }
+}
+
+impl<'a, Coord> PointCollection<'a, Coord> for &'a DashedPath<Coord> {
+    type Point = &'a Coord;
+    type IntoIter = &'a [Coord];
+
+    fn point_iter(self) -> Self::IntoIter {
+        &self.points
+    }
+}
+
+impl<Coord, DB: DrawingBackend> Drawable<DB> for DashedPath<Coord> {
+    #[allow(clippy::cast_possible_truncation)]
+    fn draw<I: Iterator<Item = BackendCoord>>(
+        &self,
+        pos: I,
+        backend: &mut DB,
+        _: (u32, u32),
+    ) -> Result<(), DrawingErrorKind<DB::ErrorType>> {
+        let to_backend = |(x, y): (f64, f64)| (x.round() as i32, y.round() as i32);
+
+        let mut points = pos.map(|(x, y)| (f64::from(x), f64::from(y)));
+        let Some(mut previous) = points.next() else {
+            return Ok(());
+        };
+
+        let mut dash = vec![to_backend(previous)];
+        let mut drawing = true;
+        let mut phase = 0.0;
+
+        for point in points {
+            let (dx, dy) = (point.0 - previous.0, point.1 - previous.1);
+            let length = dx.hypot(dy);
+            let mut travelled = 0.0;
+
+            while length - travelled > f64::EPSILON {
+                let state_length = if drawing { self.dash } else { self.gap };
+                let step = (state_length - phase).min(length - travelled);
+                travelled += step;
+                phase += step;
+
+                let t = travelled / length;
+                let current = (previous.0 + dx * t, previous.1 + dy * t);
+                if drawing {
+                    dash.push(to_backend(current));
+                }
+
+                if phase >= state_length {
+                    phase = 0.0;
+                    if drawing {
+                        backend.draw_path(dash.drain(..), &self.style)?;
+                    } else {
+                        dash.push(to_backend(current));
+                    }
+                    drawing = !drawing;
+                }
+            }
+
+            previous = point;
+        }
+
+        if drawing && dash.len() > 1 {
+            backend.draw_path(dash, &self.style)?;
+        }
+
+        Ok(())
+    }
+}
@@ -9,5 +9,8 @@
 mod colorbar;
 pub use self::colorbar::Colorbar;
 
+mod dashed;
+pub use self::dashed::DashedPath;
+
 mod loadbar;
 pub use self::loadbar::Loadbar;
@@ -21,3 +21,5 @@
 pub mod palette;
 
 pub mod types;
+
+pub mod window;
@@ -6,7 +6,10 @@
 
 //! Data structures for palettes
 
-use serde::Deserialize;
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer};
+
+use palette::Srgb;
 
 use plotters::style::Color;
 use plotters::style::Palette;
@@ -108,6 +111,24 @@ pub fn pick(&self, index: usize) -> RGBColor {
     }
 }
 
+/// A color parsed from a hexadecimal string, such as `"#e41a1c"`
+#[derive(Clone, Copy, Debug)]
+pub struct HexColor(pub RGBColor);
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        let color: Srgb<u8> = string
+            .parse()
+            .map_err(|_| D::Error::custom(format!("Not a hexadecimal color: {string}")))?;
+        let (r, g, b) = color.into_components();
+        Ok(Self(RGBColor(r, g, b)))
+    }
+}
+
 /// Wrapper palette for dark theme
 ///
 /// 1. `#000000` <img src="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAADIAAAAPCAIAAAApuQuVAAAAGUlEQVR4nGNgGAWjYBSMglEwCkbBKBgmAAAI2QABplD5BgAAAABJRU5ErkJggg==">
@@ -0,0 +1,306 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for calendar-aligned time windows
+
+use miette::{miette, IntoDiagnostic, Report};
+
+use serde::Deserialize;
+
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, Month, OffsetDateTime, UtcOffset};
+
+use time_tz::{system::get_timezone, OffsetDateTimeExt, PrimitiveDateTimeExt, TimeZone};
+
+use crate::duration::{duration_to_query, Iso8601Duration};
+
+/// A time window aligned to the calendar
+///
+/// Windows start at midnight in the local timezone, and they end either now
+/// or at the start of the following window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeWindow {
+    /// Since midnight
+    Today,
+
+    /// From midnight to midnight of the previous day
+    Yesterday,
+
+    /// Since midnight on Monday
+    ThisWeek,
+
+    /// The previous full week, from Monday to Monday
+    LastWeek,
+
+    /// Since midnight on the first day of the month
+    ThisMonth,
+
+    /// The previous full month
+    LastMonth,
+
+    /// Since midnight on the first day of the year
+    ThisYear,
+}
+
+impl TimeWindow {
+    /// Compute start and end of the window at an instant in a timezone
+    ///
+    /// Return nothing if the window is outside the supported range of dates.
+    #[must_use]
+    pub fn bounds<T: TimeZone>(
+        self,
+        now: OffsetDateTime,
+        timezone: &T,
+    ) -> Option<(OffsetDateTime, OffsetDateTime)> {
+        let today = now.to_timezone(timezone).date();
+        let monday = today.checked_sub(Duration::days(i64::from(
+            today.weekday().number_days_from_monday(),
+        )))?;
+        let first_of_month = Date::from_calendar_date(today.year(), today.month(), 1).ok()?;
+
+        let (start, end) = match self {
+            Self::Today => (today, None),
+            Self::Yesterday => (today.previous_day()?, Some(today)),
+            Self::ThisWeek => (monday, None),
+            Self::LastWeek => (monday.checked_sub(Duration::weeks(1))?, Some(monday)),
+            Self::ThisMonth => (first_of_month, None),
+            Self::LastMonth => {
+                let year = if today.month() == Month::January {
+                    today.year() - 1
+                } else {
+                    today.year()
+                };
+                let start = Date::from_calendar_date(year, today.month().previous(), 1).ok()?;
+                (start, Some(first_of_month))
+            }
+            Self::ThisYear => (
+                Date::from_calendar_date(today.year(), Month::January, 1).ok()?,
+                None,
+            ),
+        };
+
+        Some((
+            midnight(start, timezone)?,
+            end.map_or(Some(now), |end| midnight(end, timezone))?,
+        ))
+    }
+
+    /// Compute start and end of the window now in the local timezone
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the local timezone is not known or the window is
+    /// outside the supported range of dates
+    pub fn local_bounds(self) -> Result<(OffsetDateTime, OffsetDateTime), Report> {
+        let timezone = get_timezone().into_diagnostic()?;
+        self.bounds(OffsetDateTime::now_utc(), timezone)
+            .ok_or_else(|| miette!("cannot compute time window {self:?}"))
+    }
+}
+
+/// Return the start and end of the queried period as query expressions
+///
+/// Calendar-aligned windows take precedence over rolling periods ending now.
+///
+/// # Errors
+///
+/// Return an error when neither a window nor a period is given, or when they
+/// cannot be formatted
+pub fn query_bounds(
+    how_long_ago: Option<&Iso8601Duration>,
+    time_window: Option<TimeWindow>,
+) -> Result<(String, String), Report> {
+    match (time_window, how_long_ago) {
+        (Some(time_window), _) => {
+            let (start, end) = time_window.local_bounds()?;
+            Ok((format_instant(start)?, format_instant(end)?))
+        }
+        (None, Some(how_long_ago)) => Ok((
+            format!(
+                "now() - {}",
+                duration_to_query(&how_long_ago.duration).into_diagnostic()?
+            ),
+            "now()".to_owned(),
+        )),
+        (None, None) => Err(miette!("missing setting how_long_ago or time_window")),
+    }
+}
+
+/// Return a `tz()` clause aligning `GROUP BY time()` intervals to the local
+/// timezone
+///
+/// # Errors
+///
+/// Return an error when the local timezone is not known
+pub fn local_tz_clause() -> Result<String, Report> {
+    let timezone = get_timezone().into_diagnostic()?;
+    Ok(tz_clause(timezone))
+}
+
+/// Return a `tz()` clause aligning `GROUP BY time()` intervals to a timezone
+#[must_use]
+pub fn tz_clause<T: TimeZone>(timezone: &T) -> String {
+    format!("tz('{}')", timezone.name())
+}
+
+/// Return the start of the `GROUP BY time()` interval containing an instant
+///
+/// With a `tz()` clause, intervals are aligned to multiples of their length
+/// in local time since the Unix epoch, e.g. daily intervals start at local
+/// midnight.
+#[must_use]
+pub fn interval_start<T: TimeZone>(
+    instant: OffsetDateTime,
+    length: Duration,
+    timezone: &T,
+) -> OffsetDateTime {
+    let length = length.whole_seconds();
+    if length <= 0 {
+        return instant;
+    }
+    let local = instant.to_timezone(timezone);
+    let local_seconds = local.unix_timestamp() + i64::from(local.offset().whole_seconds());
+    instant - Duration::seconds(local_seconds.rem_euclid(length))
+}
+
+/// Return the start of the current `GROUP BY time()` interval in the local
+/// timezone as a query expression
+///
+/// # Errors
+///
+/// Return an error when the local timezone is not known, or when the instant
+/// cannot be formatted
+pub fn local_interval_start(length: Duration) -> Result<String, Report> {
+    let timezone = get_timezone().into_diagnostic()?;
+    format_instant(interval_start(OffsetDateTime::now_utc(), length, timezone))
+}
+
+/// Format an instant as a time literal for queries
+fn format_instant(instant: OffsetDateTime) -> Result<String, Report> {
+    let instant = instant
+        .to_offset(UtcOffset::UTC)
+        .format(&Rfc3339)
+        .into_diagnostic()?;
+    Ok(format!("'{instant}'"))
+}
+
+/// Return the instant of the start of a date in a timezone
+///
+/// When midnight is skipped by a daylight saving time transition, the first
+/// valid local time after it is used.
+fn midnight<T: TimeZone>(date: Date, timezone: &T) -> Option<OffsetDateTime> {
+    let midnight = date.midnight();
+    (0..24 * 4)
+        .map(|quarter| midnight + Duration::minutes(15 * quarter))
+        .find_map(|time| time.assume_timezone(timezone).take_first())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use time::macros::datetime;
+
+    use time_tz::timezones::db::america::SAO_PAULO;
+    use time_tz::timezones::db::europe::COPENHAGEN;
+
+    #[test]
+    fn days_across_daylight_saving_time() {
+        let now = datetime!(2023-03-26 12:00 UTC);
+
+        let today = TimeWindow::Today.bounds(now, COPENHAGEN);
+        assert_eq!(today, Some((datetime!(2023-03-25 23:00 UTC), now)));
+
+        let yesterday = TimeWindow::Yesterday.bounds(now, COPENHAGEN);
+        assert_eq!(
+            yesterday,
+            Some((
+                datetime!(2023-03-24 23:00 UTC),
+                datetime!(2023-03-25 23:00 UTC)
+            ))
+        );
+    }
+
+    #[test]
+    fn day_with_skipped_midnight() {
+        // Clocks moved from 00:00 to 01:00 at the start of daylight saving time
+        let now = datetime!(2018-11-04 12:00 UTC);
+
+        let today = TimeWindow::Today.bounds(now, SAO_PAULO);
+        assert_eq!(today, Some((datetime!(2018-11-04 03:00 UTC), now)));
+
+        let yesterday = TimeWindow::Yesterday.bounds(datetime!(2018-11-05 12:00 UTC), SAO_PAULO);
+        assert_eq!(
+            yesterday,
+            Some((
+                datetime!(2018-11-04 03:00 UTC),
+                datetime!(2018-11-05 02:00 UTC)
+            ))
+        );
+    }
+
+    #[test]
+    fn intervals_aligned_to_local_time() {
+        let now = datetime!(2023-07-02 20:00 UTC);
+
+        let day = interval_start(now, Duration::DAY, COPENHAGEN);
+        assert_eq!(day, datetime!(2023-07-01 22:00 UTC));
+
+        let hours = interval_start(now, Duration::hours(6), COPENHAGEN);
+        assert_eq!(hours, datetime!(2023-07-02 16:00 UTC));
+
+        let day = interval_start(now, Duration::DAY, SAO_PAULO);
+        assert_eq!(day, datetime!(2023-07-02 03:00 UTC));
+
+        assert_eq!(tz_clause(COPENHAGEN), "tz('Europe/Copenhagen')");
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let now = datetime!(2023-07-02 20:00 UTC);
+
+        let this_week = TimeWindow::ThisWeek.bounds(now, COPENHAGEN);
+        assert_eq!(this_week, Some((datetime!(2023-06-25 22:00 UTC), now)));
+
+        let last_week = TimeWindow::LastWeek.bounds(now, COPENHAGEN);
+        assert_eq!(
+            last_week,
+            Some((
+                datetime!(2023-06-18 22:00 UTC),
+                datetime!(2023-06-25 22:00 UTC)
+            ))
+        );
+    }
+
+    #[test]
+    fn months_and_years() {
+        let now = datetime!(2023-01-15 08:00 UTC);
+
+        let this_month = TimeWindow::ThisMonth.bounds(now, COPENHAGEN);
+        assert_eq!(this_month, Some((datetime!(2022-12-31 23:00 UTC), now)));
+
+        let last_month = TimeWindow::LastMonth.bounds(now, COPENHAGEN);
+        assert_eq!(
+            last_month,
+            Some((
+                datetime!(2022-11-30 23:00 UTC),
+                datetime!(2022-12-31 23:00 UTC)
+            ))
+        );
+
+        let this_year = TimeWindow::ThisYear.bounds(now, COPENHAGEN);
+        assert_eq!(this_year, Some((datetime!(2022-12-31 23:00 UTC), now)));
+    }
+
+    #[test]
+    fn local_date_differs_from_utc_date() {
+        let now = datetime!(2023-05-31 22:30 UTC);
+
+        let this_month = TimeWindow::ThisMonth.bounds(now, COPENHAGEN);
+        assert_eq!(this_month, Some((datetime!(2023-05-31 22:00 UTC), now)));
+    }
+}
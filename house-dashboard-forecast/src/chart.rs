@@ -0,0 +1,214 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Functions for generating chart
+
+use tracing::{debug, info, warn};
+
+use chrono::Local;
+
+use plotters::{
+    backend::{BitMapBackend, DrawingBackend},
+    coord::Shift,
+    drawing::{DrawingArea, IntoDrawingArea},
+    element::{PathElement, Rectangle, Text},
+    style::{
+        text_anchor::{HPos, Pos, VPos},
+        Color, IntoFont,
+    },
+};
+
+use house_dashboard_common::{configuration::StyleConfiguration, palette::SystemColor};
+
+use crate::data::Forecast;
+use crate::icon::{draw_icon, RAIN_COLOR};
+use crate::Error;
+use crate::ForecastConfiguration;
+
+/// Space between elements of a column in pixels
+const SPACING: i32 = 4;
+
+/// Draw a weather forecast chart
+///
+/// # Errors
+///
+/// Return and error when chart generation failed
+pub fn draw_forecast(
+    forecast: &ForecastConfiguration,
+    forecasts: &[Forecast],
+    style: &StyleConfiguration,
+    backend: BitMapBackend,
+) -> Result<(), Error> {
+    info!("Drawing forecast '{}'", forecast.title.to_lowercase());
+
+    let root = backend.into_drawing_area();
+    root.fill(&style.system_palette.pick(SystemColor::Background))?;
+
+    // Draw the title manually and create a new margin area
+    let title_height = draw_title(forecast.title.as_str(), style, &root)?;
+    let new_root = root.margin(title_height, 5, 5, 5);
+
+    if forecasts.is_empty() {
+        warn!("No forecasts to draw");
+        return Ok(());
+    }
+
+    let max_precipitation = forecast.max_precipitation.unwrap_or_else(|| {
+        forecasts
+            .iter()
+            .filter_map(|forecast| forecast.precipitation)
+            .fold(0.0, f64::max)
+    });
+
+    let columns = new_root.split_evenly((1, forecasts.len()));
+    for (column, period) in columns.iter().zip(forecasts) {
+        draw_period(forecast, style, column, period, max_precipitation)?;
+    }
+
+    Ok(())
+}
+
+/// Draw title
+fn draw_title<DB: DrawingBackend>(
+    title: &str,
+    style: &StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+) -> Result<i32, Error> {
+    let title_font = (style.font_name.as_str(), 16.0 * style.font_scale).into_font();
+    let pos = Pos::new(HPos::Center, VPos::Top);
+
+    let (width, _height) = root.dim_in_pixel();
+
+    let (_box_width, box_height) = title_font.box_size(title).map_err(|_| Error::Font)?;
+    let box_height = i32::try_from(box_height)?;
+    let box_x = i32::try_from(width)? / 2;
+    let box_y = box_height / 2;
+
+    let vertical_skip = 5;
+
+    root.draw(&Text::new(
+        title,
+        (box_x, box_y + vertical_skip),
+        title_font
+            .color(&style.system_palette.pick(SystemColor::Foreground))
+            .pos(pos),
+    ))?;
+
+    Ok(box_height * 2)
+}
+
+/// Draw the forecast for a single period in a column
+///
+/// The column contains the period label, the weather condition icon, the
+/// highest and lowest temperatures, and a precipitation bar scaled to the
+/// largest precipitation.
+fn draw_period<DB: DrawingBackend>(
+    forecast: &ForecastConfiguration,
+    style: &StyleConfiguration,
+    column: &DrawingArea<DB, Shift>,
+    period: &Forecast,
+    max_precipitation: f64,
+) -> Result<(), Error> {
+    debug!("Drawing forecast for {}", period.instant);
+
+    let (width, height) = column.dim_in_pixel();
+    let (width, height) = (i32::try_from(width)?, i32::try_from(height)?);
+    let middle = width / 2;
+
+    let foreground = style.system_palette.pick(SystemColor::Foreground);
+    let light_foreground = style.system_palette.pick(SystemColor::LightForeground);
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale).into_font();
+    let temperature_font = (style.font_name.as_str(), 10.0 * style.font_scale).into_font();
+    let top_position = Pos::new(HPos::Center, VPos::Top);
+
+    let (_, label_height) = label_font.box_size("0").map_err(|_| Error::Font)?;
+    let (_, temperature_height) = temperature_font.box_size("0").map_err(|_| Error::Font)?;
+    let (label_height, temperature_height) = (
+        i32::try_from(label_height)?,
+        i32::try_from(temperature_height)?,
+    );
+
+    let label = period
+        .instant
+        .with_timezone(&Local)
+        .format(forecast.xlabel_format())
+        .to_string();
+    column.draw(&Text::new(
+        label,
+        (middle, SPACING),
+        label_font.color(&foreground).pos(top_position),
+    ))?;
+
+    let icon_top = label_height + 2 * SPACING;
+    let icon_size = (width - 2 * SPACING).min(height * 3 / 10).max(1);
+    draw_icon(
+        column,
+        period.condition,
+        (middle, icon_top + icon_size / 2),
+        icon_size,
+        style,
+    )?;
+
+    let precision = forecast.precision.unwrap_or(0);
+    let unit = forecast.unit.as_deref().unwrap_or_default();
+    let temperature = |value: Option<f64>| {
+        value.map_or_else(
+            || "-".to_owned(),
+            |value| format!("{value:.precision$}{unit}"),
+        )
+    };
+    let high_y = icon_top + icon_size + SPACING;
+    let low_y = high_y + temperature_height + SPACING;
+    column.draw(&Text::new(
+        temperature(period.high),
+        (middle, high_y),
+        temperature_font.color(&foreground).pos(top_position),
+    ))?;
+    column.draw(&Text::new(
+        temperature(period.low),
+        (middle, low_y),
+        temperature_font.color(&light_foreground).pos(top_position),
+    ))?;
+
+    let bar_top = low_y + temperature_height + 2 * SPACING;
+    let bar_bottom = height - label_height - 2 * SPACING;
+    let bar_color = forecast
+        .precipitation_color
+        .map_or(RAIN_COLOR, |color| color.0);
+    column.draw(&PathElement::new(
+        vec![(SPACING, bar_bottom), (width - SPACING, bar_bottom)],
+        style.system_palette.pick(SystemColor::LightBackground),
+    ))?;
+
+    if let Some(precipitation) = period.precipitation {
+        if precipitation > 0.0 && max_precipitation > 0.0 {
+            let fraction = (precipitation / max_precipitation).min(1.0);
+            let bar_height = scale(bar_bottom - bar_top, fraction);
+            column.draw(&Rectangle::new(
+                [
+                    (width / 4, bar_bottom - bar_height),
+                    (width * 3 / 4, bar_bottom),
+                ],
+                bar_color.filled(),
+            ))?;
+        }
+
+        let unit = forecast.precipitation_unit.as_deref().unwrap_or("mm");
+        column.draw(&Text::new(
+            format!("{precipitation:.1}{unit}"),
+            (middle, bar_bottom + SPACING),
+            label_font.color(&foreground).pos(top_position),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Scale a length in pixels by a fraction
+#[allow(clippy::cast_possible_truncation)]
+fn scale(length: i32, fraction: f64) -> i32 {
+    (f64::from(length) * fraction).round() as i32
+}
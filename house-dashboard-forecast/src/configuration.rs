@@ -0,0 +1,81 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data structures for parsing configuration
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use time::Duration;
+
+use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::palette::HexColor;
+
+/// Chart configuration for weather forecast charts
+#[derive(Debug, Deserialize)]
+pub struct ForecastConfiguration {
+    /// Chart title
+    pub title: String,
+
+    /// Precision of temperatures
+    pub precision: Option<usize>,
+
+    /// Temperature unit
+    pub unit: Option<String>,
+
+    /// Precipitation unit
+    pub precipitation_unit: Option<String>,
+
+    /// InfluxDB database
+    pub database: String,
+
+    /// InfluxDB measurement
+    pub measurement: String,
+
+    /// InfluxDB tag values to filter by
+    pub tag_filter: Option<BTreeMap<String, String>>,
+
+    /// Time of forecasts from now
+    pub how_far_ahead: Iso8601Duration,
+
+    /// Length of each forecast period
+    pub how_often: Option<Iso8601Duration>,
+
+    /// Format of period labels
+    pub xlabel_format: Option<String>,
+
+    /// Precipitation drawn as a full bar, or the largest forecast if missing
+    pub max_precipitation: Option<f64>,
+
+    /// Color of precipitation bars
+    pub precipitation_color: Option<HexColor>,
+}
+
+impl ForecastConfiguration {
+    /// Return the length of each forecast period, one day by default
+    #[must_use]
+    pub fn period(&self) -> Duration {
+        self.how_often
+            .as_ref()
+            .map_or(Duration::DAY, |how_often| how_often.duration)
+    }
+
+    /// Return the format of period labels
+    ///
+    /// Daily or longer periods are labelled with the weekday by default, and
+    /// shorter periods with the time of day.
+    #[must_use]
+    pub fn xlabel_format(&self) -> &str {
+        self.xlabel_format.as_deref().unwrap_or_else(|| {
+            if self.period() >= Duration::DAY {
+                "%a"
+            } else {
+                "%H:%M"
+            }
+        })
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for weather forecast chart data
+
+use core::str::FromStr;
+
+use chrono::{DateTime, Utc};
+
+/// Weather forecast for a single period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    /// Start of the period
+    pub instant: DateTime<Utc>,
+
+    /// Weather condition, if known
+    pub condition: Option<Condition>,
+
+    /// Highest temperature
+    pub high: Option<f64>,
+
+    /// Lowest temperature
+    pub low: Option<f64>,
+
+    /// Amount of precipitation
+    pub precipitation: Option<f64>,
+}
+
+/// Weather condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// Clear sky
+    Clear,
+
+    /// Sun and clouds
+    PartlyCloudy,
+
+    /// Overcast sky
+    Cloudy,
+
+    /// Fog or mist
+    Fog,
+
+    /// Light rain
+    Drizzle,
+
+    /// Rain
+    Rain,
+
+    /// Mixed rain and snow
+    Sleet,
+
+    /// Snow
+    Snow,
+
+    /// Thunderstorm
+    Thunderstorm,
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(condition: &str) -> Result<Self, Self::Err> {
+        match condition.trim().to_lowercase().as_str() {
+            "clear" => Ok(Self::Clear),
+            "partly-cloudy" => Ok(Self::PartlyCloudy),
+            "cloudy" => Ok(Self::Cloudy),
+            "fog" => Ok(Self::Fog),
+            "drizzle" => Ok(Self::Drizzle),
+            "rain" => Ok(Self::Rain),
+            "sleet" => Ok(Self::Sleet),
+            "snow" => Ok(Self::Snow),
+            "thunderstorm" => Ok(Self::Thunderstorm),
+            other => Err(format!("unknown weather condition '{other}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_conditions() {
+        assert_eq!("clear".parse(), Ok(Condition::Clear));
+        assert_eq!("Partly-Cloudy".parse(), Ok(Condition::PartlyCloudy));
+        assert_eq!(" thunderstorm ".parse(), Ok(Condition::Thunderstorm));
+    }
+
+    #[test]
+    fn parse_unknown_condition() {
+        assert!("hail".parse::<Condition>().is_err());
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Functions for drawing weather condition icons
+
+use std::f64::consts::PI;
+
+use plotters::{
+    backend::DrawingBackend,
+    coord::Shift,
+    drawing::DrawingArea,
+    element::{Circle, PathElement, Polygon, Rectangle, Text},
+    style::{
+        text_anchor::{HPos, Pos, VPos},
+        Color, IntoFont, RGBColor,
+    },
+};
+
+use house_dashboard_common::{configuration::StyleConfiguration, palette::SystemColor};
+
+use crate::data::Condition;
+use crate::Error;
+
+/// Color of the sun and of lightning
+const SUN_COLOR: RGBColor = RGBColor(255, 191, 0);
+
+/// Color of rain and drizzle
+pub const RAIN_COLOR: RGBColor = RGBColor(55, 126, 184);
+
+/// Horizontal positions of drops relative to the icon size
+const DROPS: [f64; 3] = [-0.25, 0.0, 0.25];
+
+/// Draw an icon for a weather condition
+///
+/// Icons are vector glyphs drawn in a square centred on a point.
+/// Unknown conditions are drawn as a question mark.
+pub fn draw_icon<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    condition: Option<Condition>,
+    center: (i32, i32),
+    size: i32,
+    style: &StyleConfiguration,
+) -> Result<(), Error> {
+    let icon = Icon {
+        area,
+        center,
+        size,
+        stroke: u32::try_from((size / 16).max(1))?,
+        cloud_color: style.system_palette.pick(SystemColor::Middle),
+        snow_color: style.system_palette.pick(SystemColor::Foreground),
+    };
+
+    match condition {
+        Some(Condition::Clear) => icon.sun((0.0, 0.0), 0.2)?,
+        Some(Condition::PartlyCloudy) => {
+            icon.sun((0.15, -0.15), 0.14)?;
+            icon.cloud((-0.05, 0.1), 0.75)?;
+        }
+        Some(Condition::Cloudy) => icon.cloud((0.0, 0.0), 0.9)?,
+        Some(Condition::Fog) => icon.fog()?,
+        Some(Condition::Drizzle) => {
+            icon.cloud((0.0, -0.15), 0.8)?;
+            for x in DROPS {
+                icon.drop((x, 0.3))?;
+            }
+        }
+        Some(Condition::Rain) => {
+            icon.cloud((0.0, -0.15), 0.8)?;
+            for x in DROPS {
+                icon.streak((x, 0.18))?;
+            }
+        }
+        Some(Condition::Sleet) => {
+            icon.cloud((0.0, -0.15), 0.8)?;
+            icon.streak((DROPS[0], 0.18))?;
+            icon.flake((DROPS[1], 0.3))?;
+            icon.streak((DROPS[2], 0.18))?;
+        }
+        Some(Condition::Snow) => {
+            icon.cloud((0.0, -0.15), 0.8)?;
+            for x in DROPS {
+                icon.flake((x, 0.3))?;
+            }
+        }
+        Some(Condition::Thunderstorm) => {
+            icon.cloud((0.0, -0.15), 0.8)?;
+            icon.bolt()?;
+        }
+        None => {
+            let font = (style.font_name.as_str(), f64::from(size) / 2.0).into_font();
+            area.draw(&Text::new(
+                "?",
+                center,
+                font.color(&style.system_palette.pick(SystemColor::Foreground))
+                    .pos(Pos::new(HPos::Center, VPos::Center)),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A square area for drawing an icon
+///
+/// Shapes are positioned and sized relative to the icon size.
+struct Icon<'a, DB: DrawingBackend> {
+    /// Drawing area
+    area: &'a DrawingArea<DB, Shift>,
+
+    /// Centre of the icon
+    center: (i32, i32),
+
+    /// Size of the icon
+    size: i32,
+
+    /// Width of lines
+    stroke: u32,
+
+    /// Color of clouds and fog
+    cloud_color: RGBColor,
+
+    /// Color of snow flakes
+    snow_color: RGBColor,
+}
+
+impl<DB: DrawingBackend> Icon<'_, DB> {
+    /// Convert a position relative to the icon centre to pixel coordinates
+    #[allow(clippy::cast_possible_truncation)]
+    fn point(&self, (x, y): (f64, f64)) -> (i32, i32) {
+        let size = f64::from(self.size);
+        (
+            self.center.0 + (x * size).round() as i32,
+            self.center.1 + (y * size).round() as i32,
+        )
+    }
+
+    /// Convert a length relative to the icon size to pixels
+    #[allow(clippy::cast_possible_truncation)]
+    fn length(&self, length: f64) -> i32 {
+        (length * f64::from(self.size)).round() as i32
+    }
+
+    /// Draw a sun with rays
+    fn sun(&self, center: (f64, f64), radius: f64) -> Result<(), Error> {
+        self.area.draw(&Circle::new(
+            self.point(center),
+            self.length(radius),
+            SUN_COLOR.filled(),
+        ))?;
+        for i in 0..8 {
+            let angle = f64::from(i) * PI / 4.0;
+            let ray = |distance: f64| {
+                self.point((
+                    center.0 + distance * angle.cos(),
+                    center.1 + distance * angle.sin(),
+                ))
+            };
+            self.area.draw(&PathElement::new(
+                vec![ray(radius * 1.3), ray(radius * 1.8)],
+                SUN_COLOR.stroke_width(self.stroke),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Draw a cloud with a flat bottom
+    fn cloud(&self, (x, y): (f64, f64), width: f64) -> Result<(), Error> {
+        let style = self.cloud_color.filled();
+        let bottom = y + width / 5.0;
+        self.area.draw(&Circle::new(
+            self.point((x - width / 4.0, y)),
+            self.length(width / 5.0),
+            style,
+        ))?;
+        self.area.draw(&Circle::new(
+            self.point((x, y - width / 8.0)),
+            self.length(width / 4.0),
+            style,
+        ))?;
+        self.area.draw(&Circle::new(
+            self.point((x + width / 4.0, y)),
+            self.length(width / 5.0),
+            style,
+        ))?;
+        self.area.draw(&Rectangle::new(
+            [
+                self.point((x - width / 4.0, y)),
+                self.point((x + width / 4.0, bottom)),
+            ],
+            style,
+        ))?;
+        Ok(())
+    }
+
+    /// Draw horizontal bands of fog
+    fn fog(&self) -> Result<(), Error> {
+        for (i, y) in [-0.24, -0.08, 0.08, 0.24].into_iter().enumerate() {
+            let shift = if i % 2 == 0 { -0.05 } else { 0.05 };
+            self.area.draw(&PathElement::new(
+                vec![
+                    self.point((-0.35 + shift, y)),
+                    self.point((0.35 + shift, y)),
+                ],
+                self.cloud_color.stroke_width(self.stroke * 2),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Draw a slanted rain streak starting from a point
+    fn streak(&self, (x, y): (f64, f64)) -> Result<(), Error> {
+        self.area.draw(&PathElement::new(
+            vec![self.point((x, y)), self.point((x - 0.1, y + 0.24))],
+            RAIN_COLOR.stroke_width(self.stroke),
+        ))?;
+        Ok(())
+    }
+
+    /// Draw a drizzle drop
+    fn drop(&self, center: (f64, f64)) -> Result<(), Error> {
+        self.area.draw(&Circle::new(
+            self.point(center),
+            self.length(0.05).max(1),
+            RAIN_COLOR.filled(),
+        ))?;
+        Ok(())
+    }
+
+    /// Draw a snow flake as a six-pointed star
+    fn flake(&self, (x, y): (f64, f64)) -> Result<(), Error> {
+        let arm = 0.09;
+        let stroke = (self.stroke / 2).max(1);
+        for i in 0..3 {
+            let angle = f64::from(i) * PI / 3.0 + PI / 2.0;
+            let (dx, dy) = (arm * angle.cos(), arm * angle.sin());
+            self.area.draw(&PathElement::new(
+                vec![self.point((x - dx, y - dy)), self.point((x + dx, y + dy))],
+                self.snow_color.stroke_width(stroke),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Draw a lightning bolt below a cloud
+    fn bolt(&self) -> Result<(), Error> {
+        let points = [
+            (0.06, 0.08),
+            (-0.1, 0.3),
+            (0.0, 0.3),
+            (-0.08, 0.48),
+            (0.14, 0.22),
+            (0.03, 0.22),
+            (0.12, 0.08),
+        ]
+        .into_iter()
+        .map(|point| self.point(point))
+        .collect::<Vec<_>>();
+        self.area.draw(&Polygon::new(points, SUN_COLOR.filled()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright Claudio Mattera 2023.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for fetching data for weather forecast charts
+
+use tracing::{debug, warn};
+
+use miette::{IntoDiagnostic, Report, WrapErr};
+
+use house_dashboard_common::duration::duration_to_query;
+use house_dashboard_common::window::{local_interval_start, local_tz_clause};
+
+use house_dashboard_influxdb::Error as InfluxDBError;
+use house_dashboard_influxdb::{Frame, InfluxDBClient, Value};
+
+use crate::configuration::ForecastConfiguration;
+use crate::data::{Condition, Forecast};
+
+/// Field containing the weather condition
+const CONDITION_FIELD: &str = "condition";
+
+/// Field containing the highest temperature
+const HIGH_FIELD: &str = "temperature_max";
+
+/// Field containing the lowest temperature
+const LOW_FIELD: &str = "temperature_min";
+
+/// Field containing the amount of precipitation
+const PRECIPITATION_FIELD: &str = "precipitation";
+
+/// Fetch data for weather forecast
+///
+/// Forecasts are fetched from the start of the current period up to
+/// `how_far_ahead` from now, and they are aggregated per period in the local
+/// timezone, so that there is a single forecast for each period.
+///
+/// # Errors
+///
+/// Return and error when data could not be fetched
+pub async fn fetch_data(
+    influxdb_client: &InfluxDBClient,
+    forecast_configuration: &ForecastConfiguration,
+) -> Result<Vec<Forecast>, Report> {
+    let filters = forecast_configuration
+        .tag_filter
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!(r#" AND "{key}" = '{}'"#, value.replace('\'', r"\'")))
+        .collect::<Vec<String>>()
+        .concat();
+
+    let period = forecast_configuration.period();
+
+    let query = format!(
+        "SELECT last({CONDITION_FIELD}) AS {CONDITION_FIELD},
+        max({HIGH_FIELD}) AS {HIGH_FIELD},
+        min({LOW_FIELD}) AS {LOW_FIELD},
+        sum({PRECIPITATION_FIELD}) AS {PRECIPITATION_FIELD}
+        FROM {database}.autogen.{measurement}
+        WHERE time >= {start} AND time < now() + {how_far_ahead}{filters}
+        GROUP BY time({period}) FILL(none) {tz}",
+        database = forecast_configuration.database,
+        measurement = forecast_configuration.measurement,
+        start = local_interval_start(period)?,
+        how_far_ahead =
+            duration_to_query(&forecast_configuration.how_far_ahead.duration).into_diagnostic()?,
+        period = duration_to_query(&period).into_diagnostic()?,
+        tz = local_tz_clause()?,
+    );
+
+    debug!("Query: {}", query);
+
+    let frames = match influxdb_client.fetch_frames(&query).await {
+        Ok(frames) => Ok(frames),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => Ok(Vec::new()),
+        other => other,
+    }
+    .wrap_err("cannot fetch forecasts")?;
+
+    let mut forecasts: Vec<Forecast> = frames.iter().flat_map(parse_frame).collect();
+    forecasts.sort_by_key(|forecast| forecast.instant);
+
+    Ok(forecasts)
+}
+
+/// Parse forecasts from the rows of a frame
+fn parse_frame(frame: &Frame) -> Vec<Forecast> {
+    let condition_index = frame.column_index(CONDITION_FIELD);
+    let high_index = frame.column_index(HIGH_FIELD);
+    let low_index = frame.column_index(LOW_FIELD);
+    let precipitation_index = frame.column_index(PRECIPITATION_FIELD);
+
+    frame
+        .rows()
+        .iter()
+        .map(|(instant, values)| {
+            let float = |index: Option<usize>| {
+                index
+                    .and_then(|index| values.get(index))
+                    .and_then(Value::as_f64)
+            };
+            let condition = condition_index
+                .and_then(|index| values.get(index))
+                .and_then(Value::as_str)
+                .and_then(|condition| {
+                    condition
+                        .parse::<Condition>()
+                        .map_err(|error| warn!("{}", error))
+                        .ok()
+                });
+            Forecast {
+                instant: *instant,
+                condition,
+                high: float(high_index),
+                low: float(low_index),
+                precipitation: float(precipitation_index),
+            }
+        })
+        .collect()
+}
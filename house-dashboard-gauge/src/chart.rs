@@ -0,0 +1,381 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Functions for generating chart
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::hash::BuildHasher;
+
+use tracing::{debug, info, warn};
+
+use plotters::{
+    backend::{BitMapBackend, DrawingBackend},
+    coord::Shift,
+    drawing::{DrawingArea, IntoDrawingArea},
+    element::{Circle, Polygon, Text},
+    style::{
+        text_anchor::{HPos, Pos, VPos},
+        Color, IntoFont, RGBColor,
+    },
+};
+
+use house_dashboard_common::{
+    colormap::Colormap, configuration::StyleConfiguration, palette::SystemColor,
+};
+
+use crate::configuration::Indicator;
+use crate::Error;
+use crate::GaugeConfiguration;
+
+/// Number of segments used for drawing a colormap along the arc
+const SEGMENTS: u32 = 60;
+
+/// Largest angle between consecutive points of an arc
+const ANGLE_STEP: f64 = PI / 90.0;
+
+/// Space between labels and gauges in pixels
+const SPACING: i32 = 4;
+
+/// Draw a gauge chart
+///
+/// # Errors
+///
+/// Return and error when chart generation failed
+pub fn draw_gauge<S>(
+    gauge: &GaugeConfiguration,
+    values: &HashMap<String, Option<f64>, S>,
+    style: &StyleConfiguration,
+    backend: BitMapBackend,
+) -> Result<(), Error>
+where
+    S: BuildHasher,
+{
+    info!("Drawing gauge '{}'", gauge.title.to_lowercase());
+
+    let root = backend.into_drawing_area();
+    root.fill(&style.system_palette.pick(SystemColor::Background))?;
+
+    // Draw the title manually and create a new margin area
+    let title_height = draw_title(gauge.title.as_str(), style, &root)?;
+    let new_root = root.margin(title_height, 5, 5, 5);
+
+    let names: Vec<&str> = if let Some(ref tag_values) = gauge.tag_values {
+        tag_values.iter().map(String::as_str).collect()
+    } else {
+        let mut names: Vec<&str> = values.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    };
+    if names.is_empty() {
+        warn!("No values to draw");
+        return Ok(());
+    }
+
+    let (min, max) = gauge.bounds;
+    let colormap =
+        Colormap::new_with_bounds_and_direction(gauge.colormap.as_ref(), min, max, gauge.reversed)?;
+
+    let (rows, columns) = gauge.grid(names.len());
+    let cells = new_root.split_evenly((rows, columns));
+    for (cell, name) in cells.iter().zip(names) {
+        let value = values.get(name).copied().flatten();
+        if value.is_none() {
+            warn!("Missing value for '{}'", name);
+        }
+        draw_dial(gauge, style, &colormap, cell, name, value)?;
+    }
+
+    Ok(())
+}
+
+/// Draw title
+fn draw_title<DB: DrawingBackend>(
+    title: &str,
+    style: &StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+) -> Result<i32, Error> {
+    let title_font = (style.font_name.as_str(), 16.0 * style.font_scale).into_font();
+    let pos = Pos::new(HPos::Center, VPos::Top);
+
+    let (width, _height) = root.dim_in_pixel();
+
+    let (_box_width, box_height) = title_font.box_size(title).map_err(|_| Error::Font)?;
+    let box_height = i32::try_from(box_height)?;
+    let box_x = i32::try_from(width)? / 2;
+    let box_y = box_height / 2;
+
+    let vertical_skip = 5;
+
+    root.draw(&Text::new(
+        title,
+        (box_x, box_y + vertical_skip),
+        title_font
+            .color(&style.system_palette.pick(SystemColor::Foreground))
+            .pos(pos),
+    ))?;
+
+    Ok(box_height * 2)
+}
+
+/// Draw a single gauge with its name, bounds and value
+///
+/// The gauge is a semicircular arc spanning the bounds from left to right,
+/// with the value written below its centre.
+fn draw_dial<DB: DrawingBackend>(
+    gauge: &GaugeConfiguration,
+    style: &StyleConfiguration,
+    colormap: &Colormap,
+    cell: &DrawingArea<DB, Shift>,
+    name: &str,
+    value: Option<f64>,
+) -> Result<(), Error> {
+    debug!("Drawing gauge '{}'", name);
+
+    let (width, height) = cell.dim_in_pixel();
+    let (width, height) = (i32::try_from(width)?, i32::try_from(height)?);
+
+    let foreground = style.system_palette.pick(SystemColor::Foreground);
+    let precision = gauge.precision.unwrap_or(0);
+
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale).into_font();
+    let value_font = (style.font_name.as_str(), 14.0 * style.font_scale).into_font();
+
+    let value_text = value.map_or_else(
+        || "-".to_owned(),
+        |value| {
+            let unit = gauge.unit.as_deref().unwrap_or_default();
+            format!("{value:.precision$}{unit}")
+        },
+    );
+
+    let (min, max) = gauge.bounds;
+    let bound_texts = [min, max].map(|bound| format!("{bound:.precision$}"));
+    let (mut bound_width, mut label_height) = (0, 0);
+    for bound_text in &bound_texts {
+        let (width, height) = label_font.box_size(bound_text).map_err(|_| Error::Font)?;
+        bound_width = bound_width.max(i32::try_from(width)?);
+        label_height = label_height.max(i32::try_from(height)?);
+    }
+    let (_, value_height) = value_font.box_size(&value_text).map_err(|_| Error::Font)?;
+    let value_height = i32::try_from(value_height)?;
+
+    // Leave room for the name above the arc, and for bounds and value below
+    // its centre
+    let above = label_height + 2 * SPACING;
+    let below = label_height + value_height + 3 * SPACING;
+    let radius = (width / 2 - bound_width / 2 - SPACING)
+        .min(height - above - below)
+        .max(1);
+    let thickness = (radius / 4).max(2);
+    let top = (height - above - radius - below).max(0) / 2;
+    let center = (width / 2, top + above + radius);
+
+    cell.draw(&Text::new(
+        name,
+        (width / 2, top + SPACING),
+        label_font
+            .color(&foreground)
+            .pos(Pos::new(HPos::Center, VPos::Top)),
+    ))?;
+
+    let ranges = ranges(gauge, colormap);
+    let arc = |from, to, color| {
+        draw_arc(
+            cell,
+            center,
+            (radius, thickness),
+            (from, to),
+            gauge.bounds,
+            color,
+        )
+    };
+    match gauge.indicator.unwrap_or_default() {
+        Indicator::Needle => {
+            for &(from, to, color) in &ranges {
+                arc(from, to, color)?;
+            }
+            if let Some(value) = value {
+                let length = radius - thickness / 2;
+                draw_needle(cell, center, length, value, gauge.bounds, foreground)?;
+            }
+        }
+        Indicator::Arc => {
+            arc(
+                min,
+                max,
+                style.system_palette.pick(SystemColor::LightBackground),
+            )?;
+            if let Some(value) = value {
+                for &(from, to, color) in ranges.iter().filter(|&&(from, _, _)| from < value) {
+                    arc(from, to.min(value), color)?;
+                }
+            }
+        }
+    }
+
+    let tick_position = Pos::new(HPos::Center, VPos::Top);
+    let tick_y = center.1 + SPACING;
+    let tick_xs = [
+        center.0 - radius + thickness / 2,
+        center.0 + radius - thickness / 2,
+    ];
+    for (bound_text, x) in bound_texts.into_iter().zip(tick_xs) {
+        cell.draw(&Text::new(
+            bound_text,
+            (x, tick_y),
+            label_font.color(&foreground).pos(tick_position),
+        ))?;
+    }
+
+    cell.draw(&Text::new(
+        value_text,
+        (center.0, tick_y + label_height + SPACING),
+        value_font.color(&foreground).pos(tick_position),
+    ))?;
+
+    Ok(())
+}
+
+/// Split the bounds of a gauge in colored ranges
+///
+/// Explicit thresholds take precedence over the colormap.
+/// Values below the first threshold have no range.
+fn ranges(gauge: &GaugeConfiguration, colormap: &Colormap) -> Vec<(f64, f64, RGBColor)> {
+    let (min, max) = gauge.bounds;
+
+    if let Some(ref thresholds) = gauge.thresholds {
+        let mut thresholds: Vec<(f64, RGBColor)> = thresholds
+            .iter()
+            .map(|threshold| (threshold.value, threshold.color.0))
+            .collect();
+        thresholds.sort_by(|first, second| first.0.total_cmp(&second.0));
+
+        let ends = thresholds
+            .iter()
+            .skip(1)
+            .map(|&(value, _)| value)
+            .chain([max]);
+        thresholds
+            .iter()
+            .zip(ends)
+            .map(|(&(from, color), to)| (from.max(min), to.min(max), color))
+            .filter(|&(from, to, _)| from < to)
+            .collect()
+    } else {
+        let step = (max - min) / f64::from(SEGMENTS);
+        (0..SEGMENTS)
+            .map(|i| {
+                let from = min + f64::from(i) * step;
+                let to = from + step;
+                (from, to, colormap.get_color(from + step / 2.0))
+            })
+            .collect()
+    }
+}
+
+/// Draw a section of the gauge arc between two values
+fn draw_arc<DB: DrawingBackend>(
+    cell: &DrawingArea<DB, Shift>,
+    center: (i32, i32),
+    (radius, thickness): (i32, i32),
+    (from, to): (f64, f64),
+    bounds: (f64, f64),
+    color: RGBColor,
+) -> Result<(), Error> {
+    let (start, end) = (angle(from, bounds), angle(to, bounds));
+    let outer = f64::from(radius);
+    let inner = f64::from(radius - thickness);
+
+    let steps = arc_steps(start, end);
+    let angles: Vec<f64> = (0..=steps)
+        .map(|i| start + (end - start) * f64::from(i) / f64::from(steps))
+        .collect();
+    let points: Vec<(i32, i32)> = angles
+        .iter()
+        .map(|&angle| polar(center, outer, angle))
+        .chain(
+            angles
+                .iter()
+                .rev()
+                .map(|&angle| polar(center, inner, angle)),
+        )
+        .collect();
+
+    cell.draw(&Polygon::new(points, color.filled()))?;
+
+    Ok(())
+}
+
+/// Draw a needle pointing at a value
+fn draw_needle<DB: DrawingBackend>(
+    cell: &DrawingArea<DB, Shift>,
+    center: (i32, i32),
+    length: i32,
+    value: f64,
+    bounds: (f64, f64),
+    color: RGBColor,
+) -> Result<(), Error> {
+    let angle = angle(value, bounds);
+    let base = f64::from((length / 24).max(2));
+
+    let points = vec![
+        polar(center, f64::from(length), angle),
+        polar(center, base, angle + PI / 2.0),
+        polar(center, base, angle - PI / 2.0),
+    ];
+    cell.draw(&Polygon::new(points, color.filled()))?;
+    cell.draw(&Circle::new(center, base * 1.5, color.filled()))?;
+
+    Ok(())
+}
+
+/// Compute the number of steps for drawing an arc between two angles
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn arc_steps(start: f64, end: f64) -> u32 {
+    ((start - end).abs() / ANGLE_STEP).ceil().max(1.0) as u32
+}
+
+/// Compute the angle of a value along the gauge arc
+///
+/// The lower bound is on the left, at angle π, and the upper bound is on the
+/// right, at angle 0.
+/// Values outside the bounds are clamped.
+fn angle(value: f64, (min, max): (f64, f64)) -> f64 {
+    let fraction = if max > min {
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    PI * (1.0 - fraction)
+}
+
+/// Convert polar coordinates around a center to pixel coordinates
+#[allow(clippy::cast_possible_truncation)]
+fn polar((x, y): (i32, i32), radius: f64, angle: f64) -> (i32, i32) {
+    (
+        x + (radius * angle.cos()).round() as i32,
+        y - (radius * angle.sin()).round() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angle_within_bounds() {
+        assert!((angle(0.0, (0.0, 100.0)) - PI).abs() < f64::EPSILON);
+        assert!((angle(50.0, (0.0, 100.0)) - PI / 2.0).abs() < f64::EPSILON);
+        assert!(angle(100.0, (0.0, 100.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn angle_outside_bounds() {
+        assert!((angle(-10.0, (0.0, 100.0)) - PI).abs() < f64::EPSILON);
+        assert!(angle(110.0, (0.0, 100.0)).abs() < f64::EPSILON);
+    }
+}
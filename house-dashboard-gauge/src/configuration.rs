@@ -0,0 +1,157 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data structures for parsing configuration
+
+use serde::Deserialize;
+
+use house_dashboard_common::colormap::ColormapType;
+use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::palette::HexColor;
+use house_dashboard_common::window::TimeWindow;
+
+use house_dashboard_influxdb::{LabelTemplate, TagKeys};
+
+/// Chart configuration for gauge charts
+#[derive(Debug, Deserialize)]
+pub struct GaugeConfiguration {
+    /// Chart title
+    pub title: String,
+
+    /// Precision
+    pub precision: Option<usize>,
+
+    /// Unit
+    pub unit: Option<String>,
+
+    /// InfluxDB database
+    pub database: String,
+
+    /// InfluxDB measurement
+    pub measurement: String,
+
+    /// InfluxDB field
+    pub field: String,
+
+    /// InfluxDB field scale
+    pub scale: Option<f64>,
+
+    /// InfluxDB tag names
+    pub tag: Option<TagKeys>,
+
+    /// Template for gauge labels
+    pub label: Option<LabelTemplate>,
+
+    /// InfluxDB tag values
+    pub tag_values: Option<Vec<String>>,
+
+    /// Time of data from now
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
+
+    /// Gauge bounds
+    pub bounds: (f64, f64),
+
+    /// Colormap
+    pub colormap: Option<ColormapType>,
+
+    /// Setting to reverse colormap
+    pub reversed: Option<bool>,
+
+    /// Colored ranges, instead of the colormap
+    pub thresholds: Option<Vec<GaugeThresholdConfiguration>>,
+
+    /// Indicator for the current value
+    pub indicator: Option<Indicator>,
+
+    /// Number of columns, or enough for a square grid if missing
+    pub columns: Option<usize>,
+}
+
+impl GaugeConfiguration {
+    /// Return the number of rows and columns of the grid for some gauges
+    ///
+    /// The grid has at least one row and one column.
+    #[must_use]
+    pub fn grid(&self, count: usize) -> (usize, usize) {
+        let columns = self
+            .columns
+            .unwrap_or_else(|| {
+                (1..=count)
+                    .find(|columns| columns * columns >= count)
+                    .unwrap_or(1)
+            })
+            .max(1);
+        let rows = count.div_ceil(columns).max(1);
+        (rows, columns)
+    }
+
+    /// Return the label template for gauges
+    ///
+    /// Gauges without a label are named after their tag values, or after
+    /// their field if they are not grouped by tags.
+    #[must_use]
+    pub fn label(&self) -> LabelTemplate {
+        match (self.label.as_ref(), self.tag.as_ref()) {
+            (Some(label), _) => label.clone(),
+            (None, Some(tag)) => tag.default_label(),
+            (None, None) => LabelTemplate::new(self.field.as_str()),
+        }
+    }
+}
+
+/// Configuration for a colored range of a gauge
+///
+/// A range starts at its value and ends at the next range or at the upper
+/// bound.
+#[derive(Debug, Deserialize)]
+pub struct GaugeThresholdConfiguration {
+    /// Value
+    pub value: f64,
+
+    /// Color
+    pub color: HexColor,
+}
+
+/// Indicator for the current value of a gauge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Indicator {
+    /// A needle pointing at the value over the colored ranges
+    #[default]
+    Needle,
+
+    /// An arc filled up to the value with the colors of its ranges
+    Arc,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"
+title = "HUMIDITY"
+database = "house"
+measurement = "indoor_environment"
+field = "humidity"
+bounds = [0, 100]
+"#;
+
+    #[test]
+    fn grid() {
+        let gauge: Option<GaugeConfiguration> = toml::from_str(HEADER).ok();
+        let grids = gauge.map(|gauge| [0, 1, 3, 5].map(|count| gauge.grid(count)));
+        assert_eq!(grids, Some([(1, 1), (1, 1), (2, 2), (2, 3)]));
+    }
+
+    #[test]
+    fn default_indicator() {
+        let gauge: Option<GaugeConfiguration> = toml::from_str(HEADER).ok();
+        let indicator = gauge.map(|gauge| gauge.indicator.unwrap_or_default());
+        assert_eq!(indicator, Some(Indicator::Needle));
+    }
+}
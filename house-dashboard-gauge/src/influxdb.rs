@@ -0,0 +1,72 @@
+// Copyright Claudio Mattera 2023.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for fetching data for gauge charts
+
+use std::collections::HashMap;
+
+use tracing::debug;
+
+use miette::{Report, WrapErr};
+
+use house_dashboard_common::window::query_bounds;
+
+use house_dashboard_influxdb::Error as InfluxDBError;
+use house_dashboard_influxdb::InfluxDBClient;
+
+use crate::configuration::GaugeConfiguration;
+
+/// Fetch data for gauge
+///
+/// # Errors
+///
+/// Return and error when data could not be fetched
+pub async fn fetch_data(
+    influxdb_client: &InfluxDBClient,
+    gauge_configuration: &GaugeConfiguration,
+) -> Result<HashMap<String, Option<f64>>, Report> {
+    let (since, until) = query_bounds(
+        gauge_configuration.how_long_ago.as_ref(),
+        gauge_configuration.time_window,
+    )?;
+
+    let query = format!(
+        "SELECT {scale} * last({field}) FROM {database}.autogen.{measurement}
+        WHERE time < {until} AND time > {since}{group_by}",
+        database = gauge_configuration.database,
+        scale = gauge_configuration.scale.unwrap_or(1.0),
+        field = gauge_configuration.field,
+        measurement = gauge_configuration.measurement,
+        group_by = gauge_configuration
+            .tag
+            .as_ref()
+            .map(|tag| format!(" GROUP BY {}", tag.to_group_by()))
+            .unwrap_or_default(),
+    );
+
+    debug!("Query: {}", query);
+
+    let label = gauge_configuration.label();
+
+    let time_seriess = match influxdb_client
+        .fetch_tagged_dataframes(&query, &label)
+        .await
+    {
+        Ok(time_seriess) => Ok(time_seriess),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => {
+            Ok(HashMap::new())
+        }
+        other => other,
+    }
+    .wrap_err("cannot fetch time-series")?;
+
+    let values = time_seriess
+        .into_iter()
+        .map(|(name, ts)| (name, ts.last().map(|(_instant, value)| value).copied()))
+        .collect::<HashMap<String, Option<f64>>>();
+
+    Ok(values)
+}
@@ -10,6 +10,9 @@
 
 use house_dashboard_common::colormap::ColormapType;
 use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::window::TimeWindow;
+
+use house_dashboard_influxdb::{LabelTemplate, TagKeys};
 
 /// Chart configuration for infrastructure summary charts
 #[derive(Debug, Deserialize)]
@@ -35,11 +38,17 @@ pub struct GeographicalHeatMapConfiguration {
     /// InfluxDB field scale
     pub scale: Option<f64>,
 
-    /// InfluxDB tag name
-    pub tag: String,
+    /// InfluxDB tag names
+    pub tag: TagKeys,
+
+    /// Template for region names
+    pub label: Option<LabelTemplate>,
 
     /// Time of data from now
-    pub how_long_ago: Iso8601Duration,
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
 
     /// Heatmap bounds
     pub bounds: (f64, f64),
@@ -10,9 +10,9 @@
 
 use tracing::debug;
 
-use miette::{IntoDiagnostic, Report, WrapErr};
+use miette::{Report, WrapErr};
 
-use house_dashboard_common::duration::duration_to_query;
+use house_dashboard_common::window::query_bounds;
 
 use house_dashboard_influxdb::Error as InfluxDBError;
 use house_dashboard_influxdb::InfluxDBClient;
@@ -28,30 +28,39 @@ pub async fn fetch_data(
     influxdb_client: &InfluxDBClient,
     geographical_heatmap_configuration: &GeographicalHeatMapConfiguration,
 ) -> Result<HashMap<String, Option<f64>>, Report> {
+    let (since, until) = query_bounds(
+        geographical_heatmap_configuration.how_long_ago.as_ref(),
+        geographical_heatmap_configuration.time_window,
+    )?;
+
     let query = format!(
         "SELECT {scale} * last({field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago}
+        WHERE time < {until} AND time > {since}
         GROUP BY {tag} FILL(none)",
         database = geographical_heatmap_configuration.database,
         scale = geographical_heatmap_configuration.scale.unwrap_or(1.0),
         field = geographical_heatmap_configuration.field,
         measurement = geographical_heatmap_configuration.measurement,
-        tag = geographical_heatmap_configuration.tag,
-        how_long_ago = duration_to_query(&geographical_heatmap_configuration.how_long_ago.duration)
-            .into_diagnostic()?,
+        tag = geographical_heatmap_configuration.tag.to_group_by(),
     );
 
     debug!("Query: {}", query);
 
+    let label = geographical_heatmap_configuration
+        .label
+        .clone()
+        .unwrap_or_else(|| geographical_heatmap_configuration.tag.default_label());
+
     let time_seriess = match influxdb_client
-        .fetch_tagged_dataframes(&query, &geographical_heatmap_configuration.tag)
+        .fetch_tagged_dataframes(&query, &label)
         .await
     {
         Ok(time_seriess) => Ok(time_seriess),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => {
+            Ok(HashMap::new())
+        }
         other => other,
     }
-    .into_diagnostic()
     .wrap_err("cannot fetch time-series")?;
 
     let values = time_seriess
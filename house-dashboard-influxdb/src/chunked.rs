@@ -0,0 +1,226 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for parsing chunked InfluxDB responses
+
+use tracing::trace;
+
+use crate::influxql::{InfluxDBResponse, ResponseBuilder, SeriesSink};
+use crate::Error;
+
+/// An incremental parser for chunked InfluxDB responses
+///
+/// When queried with `chunked=true`, InfluxDB streams its response as a
+/// sequence of newline-separated JSON documents, each containing a chunk of
+/// the results.
+/// This parser is fed the response body piece by piece, and parses each
+/// chunk as soon as it is complete, so that only a single chunk is ever
+/// kept in memory as raw bytes.
+/// The series of each chunk are immediately handed to a sink, which builds
+/// the output incrementally.
+#[derive(Debug)]
+pub struct ChunkedResponseParser<S> {
+    /// Bytes of the current incomplete chunk
+    pending: Vec<u8>,
+
+    /// Number of pending bytes already scanned for newlines
+    scanned: usize,
+
+    /// Number of chunks parsed so far
+    chunks: usize,
+
+    /// Builder feeding parsed chunks to the sink
+    builder: ResponseBuilder<S>,
+}
+
+impl<S: SeriesSink> ChunkedResponseParser<S> {
+    /// Create a new parser feeding a sink
+    #[must_use]
+    pub fn new(sink: S) -> Self {
+        Self {
+            pending: Vec::new(),
+            scanned: 0,
+            chunks: 0,
+            builder: ResponseBuilder::new(sink),
+        }
+    }
+
+    /// Feed a piece of the response body to the parser
+    ///
+    /// # Errors
+    ///
+    /// Return an error when a complete chunk cannot be parsed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut start = 0;
+        while let Some(offset) = self.pending[self.scanned..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+        {
+            let end = self.scanned + offset;
+            self.parse_chunk(start, end)?;
+            start = end + 1;
+            self.scanned = start;
+        }
+
+        self.pending.drain(..start);
+        self.scanned = self.pending.len();
+
+        Ok(())
+    }
+
+    /// Parse the last chunk and return the output of the sink
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the last chunk cannot be parsed, or when the
+    /// response did not contain any chunk.
+    pub fn finish(mut self) -> Result<S::Output, Error> {
+        self.parse_chunk(0, self.pending.len())?;
+        if self.chunks == 0 {
+            return Err(Error::EmptyInfluxDBResults);
+        }
+        trace!("Parsed {} chunks", self.chunks);
+        self.builder.finish()
+    }
+
+    /// Parse a chunk from pending bytes and feed it to the sink
+    fn parse_chunk(&mut self, start: usize, end: usize) -> Result<(), Error> {
+        let line = &self.pending[start..end];
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+        let chunk: InfluxDBResponse<S::Row> = line.try_into()?;
+        self.chunks += 1;
+        self.builder.push(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::influxql::{FrameSink, TaggedSink};
+    use crate::label::LabelTemplate;
+
+    use std::collections::HashMap;
+
+    use house_dashboard_common::types::TimeSeries;
+
+    /// A response split in three chunks, with a series split in two
+    const CHUNKED_RESPONSE: &str = concat!(
+        r#"{"results":[{"statement_id":0,"series":[{"name":"temperature","tags":{"room":"Kitchen"},"columns":["time","mean"],"values":[["2023-12-01T00:00:00Z",21.5],["2023-12-01T01:00:00Z",21]],"partial":true}],"partial":true}]}"#,
+        "\n",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"temperature","tags":{"room":"Kitchen"},"columns":["time","mean"],"values":[["2023-12-01T02:00:00Z",null]]}],"partial":true}]}"#,
+        "\n",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"temperature","tags":{"room":"Bedroom"},"columns":["time","mean"],"values":[["2023-12-01T00:00:00Z",19.25]]}]}]}"#,
+        "\n",
+    );
+
+    /// Extract instants from a time-series, formatted as RFC 3339
+    fn instants(time_series: &TimeSeries) -> Vec<String> {
+        time_series
+            .iter()
+            .map(|(instant, _value)| instant.to_rfc3339())
+            .collect()
+    }
+
+    /// Extract values from a time-series
+    fn values(time_series: &TimeSeries) -> Vec<f64> {
+        time_series.iter().map(|&(_instant, value)| value).collect()
+    }
+
+    fn parse_in_pieces(size: usize) -> Result<HashMap<String, TimeSeries>, Error> {
+        let label = LabelTemplate::for_tag("room");
+        let mut parser = ChunkedResponseParser::new(TaggedSink::<f64>::new(&label));
+        for piece in CHUNKED_RESPONSE.as_bytes().chunks(size) {
+            parser.feed(piece)?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn merge_partial_series() {
+        let seriess = parse_in_pieces(CHUNKED_RESPONSE.len()).ok();
+        assert_eq!(seriess.as_ref().map(HashMap::len), Some(2));
+
+        let kitchen = seriess.as_ref().and_then(|seriess| seriess.get("Kitchen"));
+        let expected = vec![
+            "2023-12-01T00:00:00+00:00".to_owned(),
+            "2023-12-01T01:00:00+00:00".to_owned(),
+            "2023-12-01T02:00:00+00:00".to_owned(),
+        ];
+        assert_eq!(kitchen.map(instants), Some(expected));
+        let kitchen_values = kitchen.map(values);
+        assert_eq!(
+            kitchen_values.as_ref().and_then(|values| values.get(..2)),
+            Some(&[21.5, 21.0][..])
+        );
+        assert!(kitchen_values
+            .as_ref()
+            .and_then(|values| values.get(2))
+            .is_some_and(|value| value.is_nan()));
+
+        let bedroom = seriess.as_ref().and_then(|seriess| seriess.get("Bedroom"));
+        let expected = vec!["2023-12-01T00:00:00+00:00".to_owned()];
+        assert_eq!(bedroom.map(instants), Some(expected));
+        assert_eq!(bedroom.map(values), Some(vec![19.25]));
+    }
+
+    #[test]
+    fn feed_arbitrary_pieces() {
+        let expected = parse_in_pieces(CHUNKED_RESPONSE.len()).ok();
+        assert_eq!(expected.as_ref().map(HashMap::len), Some(2));
+
+        for size in [1, 7, 64, 200] {
+            let seriess = parse_in_pieces(size).ok();
+            assert_eq!(seriess.as_ref().map(HashMap::len), Some(2));
+            assert_eq!(
+                seriess.as_ref().and_then(|seriess| seriess.get("Bedroom")),
+                expected.as_ref().and_then(|seriess| seriess.get("Bedroom"))
+            );
+            assert_eq!(
+                seriess
+                    .as_ref()
+                    .and_then(|seriess| seriess.get("Kitchen"))
+                    .map(instants),
+                expected
+                    .as_ref()
+                    .and_then(|seriess| seriess.get("Kitchen"))
+                    .map(instants)
+            );
+        }
+    }
+
+    #[test]
+    fn merge_partial_frames() {
+        let mut parser = ChunkedResponseParser::new(FrameSink::default());
+        assert!(parser.feed(CHUNKED_RESPONSE.as_bytes()).is_ok());
+        let lengths = parser.finish().map(|frames| {
+            frames
+                .iter()
+                .map(|frame| frame.rows().len())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(lengths.ok(), Some(vec![3, 1]));
+    }
+
+    #[test]
+    fn parse_unterminated_last_chunk() {
+        let text = r#"{"results":[{"statement_id":0,"series":[{"name":"temperature","tags":{"room":"Kitchen"},"columns":["time","mean"],"values":[["2023-12-01T00:00:00Z",21.5]]}]}]}"#;
+        let mut parser = ChunkedResponseParser::new(FrameSink::default());
+        assert!(parser.feed(text.as_bytes()).is_ok());
+        let count = parser.finish().map(|frames| frames.len());
+        assert!(matches!(count, Ok(1)));
+    }
+
+    #[test]
+    fn empty_response() {
+        let parser = ChunkedResponseParser::new(FrameSink::default());
+        assert!(matches!(parser.finish(), Err(Error::EmptyInfluxDBResults)));
+    }
+}
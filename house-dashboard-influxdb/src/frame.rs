@@ -0,0 +1,272 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for column-aware query results
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+/// A typed value within a frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A floating point value
+    Float(f64),
+
+    /// An integer value
+    Integer(i64),
+
+    /// A boolean value
+    Boolean(bool),
+
+    /// A string value
+    String(String),
+
+    /// A missing value
+    Null,
+}
+
+impl Value {
+    /// Return the value as a float, if numeric
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(value) => Some(value),
+            Value::Integer(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    /// Return the value as an integer, if integer
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value as a boolean, if boolean
+    #[must_use]
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value as a string, if string
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Check whether the value is missing
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+}
+
+/// A column-aware frame of time-indexed rows
+///
+/// A frame corresponds to a single series in an InfluxDB result, i.e. to a
+/// single combination of tag values.
+/// Columns do not include the time column, which is stored separately in
+/// each row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Measurement name
+    name: String,
+
+    /// Tag values
+    tags: HashMap<String, String>,
+
+    /// Column names
+    columns: Vec<String>,
+
+    /// Rows
+    rows: Vec<(DateTime<Utc>, Vec<Value>)>,
+}
+
+impl Frame {
+    /// Create a new frame
+    #[must_use]
+    pub fn new(
+        name: String,
+        tags: HashMap<String, String>,
+        columns: Vec<String>,
+        rows: Vec<(DateTime<Utc>, Vec<Value>)>,
+    ) -> Self {
+        Self {
+            name,
+            tags,
+            columns,
+            rows,
+        }
+    }
+
+    /// Append rows to the frame
+    pub(crate) fn extend_rows(&mut self, rows: Vec<(DateTime<Utc>, Vec<Value>)>) {
+        self.rows.extend(rows);
+    }
+
+    /// Return the measurement name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return all tag values
+    #[must_use]
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    /// Return the value of a tag
+    #[must_use]
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Return the column names
+    #[must_use]
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Return the rows
+    #[must_use]
+    pub fn rows(&self) -> &[(DateTime<Utc>, Vec<Value>)] {
+        &self.rows
+    }
+
+    /// Return the index of a column
+    #[must_use]
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|name| name == column)
+    }
+
+    /// Return an iterator over the values of a column
+    #[must_use]
+    pub fn column(&self, column: &str) -> Option<impl Iterator<Item = (DateTime<Utc>, &Value)>> {
+        let index = self.column_index(column)?;
+        Some(
+            self.rows.iter().map(move |(instant, values)| {
+                (*instant, values.get(index).unwrap_or(&Value::Null))
+            }),
+        )
+    }
+
+    /// Return a numeric time-series from a column
+    ///
+    /// Non-numeric values are converted to `NaN`.
+    #[must_use]
+    pub fn float_column(&self, column: &str) -> Option<Vec<(DateTime<Utc>, f64)>> {
+        self.column(column).map(|values| {
+            values
+                .map(|(instant, value)| (instant, value.as_f64().unwrap_or(f64::NAN)))
+                .collect()
+        })
+    }
+
+    /// Return a numeric time-series from the first column
+    ///
+    /// Non-numeric values are converted to `NaN`.
+    #[must_use]
+    pub fn first_float_column(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.rows
+            .iter()
+            .map(|(instant, values)| {
+                let value = values.first().and_then(Value::as_f64).unwrap_or(f64::NAN);
+                (*instant, value)
+            })
+            .collect()
+    }
+
+    /// Return a string time-series from the first column
+    ///
+    /// Non-string values are converted to empty strings.
+    #[must_use]
+    pub fn first_string_column(&self) -> Vec<(DateTime<Utc>, String)> {
+        self.rows
+            .iter()
+            .map(|(instant, values)| {
+                let value = values.first().and_then(Value::as_str).unwrap_or("");
+                (*instant, value.to_owned())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    fn frame() -> Frame {
+        let instant = Utc
+            .with_ymd_and_hms(2023, 12, 1, 0, 0, 0)
+            .single()
+            .unwrap_or_default();
+        Frame::new(
+            "temperature".to_owned(),
+            HashMap::from([("room".to_owned(), "Kitchen".to_owned())]),
+            vec!["min".to_owned(), "mean".to_owned(), "count".to_owned()],
+            vec![
+                (
+                    instant,
+                    vec![Value::Float(19.5), Value::Float(21.0), Value::Integer(4)],
+                ),
+                (
+                    instant,
+                    vec![Value::Null, Value::Float(22.0), Value::Integer(2)],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn select_column_by_name() {
+        let frame = frame();
+        let means: Vec<f64> = frame
+            .float_column("mean")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_instant, value)| value)
+            .collect();
+        assert_eq!(means, vec![21.0, 22.0]);
+    }
+
+    #[test]
+    fn convert_integers_to_floats() {
+        let frame = frame();
+        let counts: Vec<f64> = frame
+            .float_column("count")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_instant, value)| value)
+            .collect();
+        assert_eq!(counts, vec![4.0, 2.0]);
+    }
+
+    #[test]
+    fn missing_column() {
+        assert!(frame().float_column("max").is_none());
+    }
+
+    #[test]
+    fn null_values_as_nan() {
+        let frame = frame();
+        let mins = frame.first_float_column();
+        assert!(mins[1].1.is_nan());
+    }
+}
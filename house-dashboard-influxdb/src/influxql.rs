@@ -8,69 +8,267 @@
 
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fmt::{Formatter, Result as FmtResult};
+use std::marker::PhantomData;
 
+use serde::de::{DeserializeOwned, Deserializer, Error as DeError, IgnoredAny, SeqAccess, Visitor};
 use serde::Deserialize;
 
 use serde_json::from_slice as from_json_slice;
 use serde_json::from_str as from_json_str;
 use serde_json::Error as SerdeJsonError;
-use serde_json::Value as JsonValue;
 
 use chrono::{DateTime, Utc};
 
-use house_dashboard_common::types::StringTimeSeries as OutputStringTimeSeries;
-use house_dashboard_common::types::TimeSeries as OutputTimeSeries;
-
+use crate::frame::{Frame, Value};
+use crate::label::LabelTemplate;
 use crate::Error;
 
-/// A tagged data-frame
+/// A consumer of the series in the first result of an InfluxDB response
 ///
-/// This is defined as a newtype only to implement `TryFrom` on it.
-#[derive(Debug, Clone)]
-pub struct TaggedDataFrame(HashMap<String, OutputTimeSeries>);
+/// Series are handed to the sink as soon as each chunk of a response is
+/// parsed, so that only the output is accumulated while the response is
+/// being received.
+pub trait SeriesSink {
+    /// Type of rows within series
+    type Row: DeserializeOwned;
+
+    /// Type of the accumulated output
+    type Output;
+
+    /// Consume a new series
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the series cannot be converted.
+    fn push(&mut self, series: Series<Self::Row>) -> Result<(), Error>;
+
+    /// Append the rows of a continuation to the last consumed series
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the rows cannot be converted.
+    fn extend(&mut self, rows: Vec<Self::Row>) -> Result<(), Error>;
+
+    /// Return the accumulated output
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the output is not valid.
+    fn finish(self) -> Result<Self::Output, Error>;
+}
+
+/// A builder feeding the chunks of an InfluxDB response to a sink
+///
+/// Only the first result of a response is considered.
+/// A series is passed as a continuation of the previous one when the latter
+/// was marked as partial and they share name and tags.
+#[derive(Debug)]
+pub struct ResponseBuilder<S> {
+    /// Sink consuming the series
+    sink: S,
+
+    /// Statement ID of the first result
+    statement_id: Option<u32>,
+
+    /// Name and tags of the last series, if marked as partial
+    partial: Option<(String, Option<HashMap<String, String>>)>,
+}
 
-impl TaggedDataFrame {
-    /// Return an iterator over each named time-series
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &OutputTimeSeries)> {
-        self.0.iter()
+impl<S: SeriesSink> ResponseBuilder<S> {
+    /// Create a new builder
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            statement_id: None,
+            partial: None,
+        }
+    }
+
+    /// Feed a chunk of a response to the sink
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the chunk contains an error or when the sink
+    /// fails to consume a series.
+    pub fn push(&mut self, chunk: InfluxDBResponse<S::Row>) -> Result<(), Error> {
+        for result in chunk.results {
+            if *self.statement_id.get_or_insert(result.statement_id) != result.statement_id {
+                continue;
+            }
+            if let Some(error) = result.error {
+                return Err(Error::InfluxDBError(error));
+            }
+            for series in result.series {
+                let continued = self
+                    .partial
+                    .take()
+                    .is_some_and(|(name, tags)| name == series.name && tags == series.tags);
+                if series.partial {
+                    self.partial = Some((series.name.clone(), series.tags.clone()));
+                }
+                if continued {
+                    self.sink.extend(series.values)?;
+                } else {
+                    self.sink.push(series)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the output of the sink
+    ///
+    /// # Errors
+    ///
+    /// Return an error when no result was fed, or when the sink output is
+    /// not valid.
+    pub fn finish(self) -> Result<S::Output, Error> {
+        if self.statement_id.is_none() {
+            return Err(Error::EmptyInfluxDBResults);
+        }
+        self.sink.finish()
     }
 }
 
-#[allow(clippy::implicit_hasher)]
-impl From<TaggedDataFrame> for HashMap<String, OutputTimeSeries> {
-    fn from(dataframe: TaggedDataFrame) -> Self {
-        dataframe.0
+/// A sink collecting series as frames
+#[derive(Debug, Default)]
+pub struct FrameSink(Vec<Frame>);
+
+impl SeriesSink for FrameSink {
+    type Row = Row;
+    type Output = Vec<Frame>;
+
+    fn push(&mut self, series: Series<Row>) -> Result<(), Error> {
+        self.0.push(Frame::try_from(series)?);
+        Ok(())
+    }
+
+    fn extend(&mut self, rows: Vec<Row>) -> Result<(), Error> {
+        let rows = time_rows(rows)?;
+        if let Some(frame) = self.0.last_mut() {
+            frame.extend_rows(rows);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Frame>, Error> {
+        Ok(self.0)
     }
 }
 
-/// A tagged string data-frame
+/// A sink collecting the first column of each series as a named time-series
 ///
-/// This is defined as a newtype only to implement `TryFrom` on it.
-#[derive(Debug, Clone)]
-pub struct TaggedStringDataFrame(HashMap<String, OutputStringTimeSeries>);
+/// Each time-series is named by rendering a label template with its tag
+/// values.
+/// Rows are converted as they are consumed, so that only the time and the
+/// first value of each row are kept.
+#[derive(Debug)]
+pub struct TaggedSink<'a, T> {
+    /// Label template
+    label: &'a LabelTemplate,
+
+    /// Time-series collected so far
+    seriess: HashMap<String, Vec<(DateTime<Utc>, T)>>,
+
+    /// Label of the last consumed series
+    last: Option<String>,
+}
 
-impl TaggedStringDataFrame {
-    /// Return an iterator over each named time-series
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &OutputStringTimeSeries)> {
-        self.0.iter()
+impl<'a, T> TaggedSink<'a, T> {
+    /// Create a new sink
+    pub fn new(label: &'a LabelTemplate) -> Self {
+        Self {
+            label,
+            seriess: HashMap::new(),
+            last: None,
+        }
     }
 }
 
-#[allow(clippy::implicit_hasher)]
-impl From<TaggedStringDataFrame> for HashMap<String, OutputStringTimeSeries> {
-    fn from(dataframe: TaggedStringDataFrame) -> Self {
-        dataframe.0
+impl<T> SeriesSink for TaggedSink<'_, T>
+where
+    T: FromValue,
+{
+    type Row = FirstColumnRow<T>;
+    type Output = HashMap<String, Vec<(DateTime<Utc>, T)>>;
+
+    fn push(&mut self, series: Series<FirstColumnRow<T>>) -> Result<(), Error> {
+        let tags = series.tags.unwrap_or_default();
+        if tags.is_empty() {
+            return Err(Error::EmptyTags);
+        }
+        let label = self.label.render(&tags)?;
+        if self.seriess.contains_key(&label) {
+            return Err(Error::DuplicateLabel(label));
+        }
+        let rows = first_column_rows(series.values)?;
+        self.seriess.insert(label.clone(), rows);
+        self.last = Some(label);
+        Ok(())
+    }
+
+    fn extend(&mut self, rows: Vec<FirstColumnRow<T>>) -> Result<(), Error> {
+        let rows = first_column_rows(rows)?;
+        if let Some(series) = self
+            .last
+            .as_ref()
+            .and_then(|label| self.seriess.get_mut(label))
+        {
+            series.extend(rows);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Output, Error> {
+        Ok(self.seriess)
+    }
+}
+
+/// A sink collecting tag values from the first series
+#[derive(Debug, Default)]
+pub struct TagValuesSink(Option<HashSet<String>>);
+
+impl SeriesSink for TagValuesSink {
+    type Row = Row;
+    type Output = HashSet<String>;
+
+    fn push(&mut self, series: Series<Row>) -> Result<(), Error> {
+        if self.0.is_none() {
+            self.0 = Some(HashSet::new());
+            self.extend(series.values)?;
+        }
+        Ok(())
+    }
+
+    fn extend(&mut self, rows: Vec<Row>) -> Result<(), Error> {
+        if let Some(ref mut tags) = self.0 {
+            for row in rows {
+                match (row.key, row.values.into_iter().next()) {
+                    (RowKey::Text(_), Some(Value::String(value))) => {
+                        tags.insert(value);
+                    }
+                    _ => return Err(Error::NotATagSeries),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<HashSet<String>, Error> {
+        self.0.ok_or(Error::EmptySeries)
     }
 }
 
 /// Top-level response from InfluxDB
 #[derive(Debug, Deserialize, Clone)]
-pub struct InfluxDBResponse {
+#[serde(bound = "R: Deserialize<'de>")]
+pub struct InfluxDBResponse<R = Row> {
     /// Results for each query
-    results: Vec<InfluxDBResult>,
+    results: Vec<InfluxDBResult<R>>,
 }
 
-impl TryFrom<&str> for InfluxDBResponse {
+impl<R: DeserializeOwned> TryFrom<&str> for InfluxDBResponse<R> {
     type Error = Error;
 
     fn try_from(text: &str) -> Result<Self, Self::Error> {
@@ -78,7 +276,7 @@ fn try_from(text: &str) -> Result<Self, Self::Error> {
     }
 }
 
-impl TryFrom<&[u8]> for InfluxDBResponse {
+impl<R: DeserializeOwned> TryFrom<&[u8]> for InfluxDBResponse<R> {
     type Error = SerdeJsonError;
 
     fn try_from(text: &[u8]) -> Result<Self, Self::Error> {
@@ -86,137 +284,66 @@ fn try_from(text: &[u8]) -> Result<Self, Self::Error> {
     }
 }
 
-impl TryFrom<(&str, &InfluxDBResponse)> for TaggedDataFrame {
+impl TryFrom<Series> for Frame {
     type Error = Error;
 
-    fn try_from((tag_name, result): (&str, &InfluxDBResponse)) -> Result<Self, Self::Error> {
-        match *result.results.first().ok_or(Error::EmptyInfluxDBResults)? {
-            InfluxDBResult::Error(ref result) => Err(Error::InfluxDBError(result.error.clone())),
-            InfluxDBResult::Success(ref result) => {
-                let seriess = result
-                    .series
-                    .iter()
-                    .map(|series| {
-                        if let Series::TimeSeries(series) = series {
-                            let values = series
-                                .values
-                                .iter()
-                                .map(|(instant, value)| {
-                                    (*instant, value.as_f64().unwrap_or(f64::NAN))
-                                })
-                                .collect();
-                            let tags = series.tags.as_ref().ok_or(Error::EmptyTags)?;
-                            let tag_value = tags
-                                .get(tag_name)
-                                .ok_or(Error::MissingTag(tag_name.into()))?;
-                            Ok((tag_value.clone(), values))
-                        } else {
-                            Err(Error::NotATimeSeries)
-                        }
-                    })
-                    .collect::<Result<HashMap<String, OutputTimeSeries>, Self::Error>>()?;
-
-                Ok(TaggedDataFrame(seriess))
-            }
-        }
-    }
-}
+    fn try_from(series: Series) -> Result<Self, Self::Error> {
+        let rows = time_rows(series.values)?;
 
-impl TryFrom<(&str, &InfluxDBResponse)> for TaggedStringDataFrame {
-    type Error = Error;
+        // The first column is always the time
+        let columns = series.columns.into_iter().skip(1).collect();
 
-    fn try_from((tag_name, result): (&str, &InfluxDBResponse)) -> Result<Self, Self::Error> {
-        match *result.results.first().ok_or(Error::EmptyInfluxDBResults)? {
-            InfluxDBResult::Error(ref result) => Err(Error::InfluxDBError(result.error.clone())),
-            InfluxDBResult::Success(ref result) => {
-                let seriess = result
-                    .series
-                    .iter()
-                    .map(|series| {
-                        if let Series::TimeSeries(series) = series {
-                            let values = series
-                                .values
-                                .iter()
-                                .map(|(instant, value)| {
-                                    (*instant, value.as_str().unwrap_or("").to_owned())
-                                })
-                                .collect();
-                            let tags = series.tags.as_ref().ok_or(Error::EmptyTags)?;
-                            let tag_value = tags
-                                .get(tag_name)
-                                .ok_or(Error::MissingTag(tag_name.into()))?;
-                            Ok((tag_value.clone(), values))
-                        } else {
-                            Err(Error::NotATimeSeries)
-                        }
-                    })
-                    .collect::<Result<HashMap<String, OutputStringTimeSeries>, Self::Error>>()?;
-
-                Ok(Self(seriess))
-            }
-        }
+        Ok(Frame::new(
+            series.name,
+            series.tags.unwrap_or_default(),
+            columns,
+            rows,
+        ))
     }
 }
 
-#[allow(clippy::implicit_hasher)]
-impl TryFrom<&InfluxDBResponse> for HashSet<String> {
-    type Error = Error;
-
-    fn try_from(result: &InfluxDBResponse) -> Result<Self, Self::Error> {
-        match *result.results.first().ok_or(Error::EmptyInfluxDBResults)? {
-            InfluxDBResult::Error(ref result) => Err(Error::InfluxDBError(result.error.clone())),
-            InfluxDBResult::Success(ref result) => {
-                let series: &Series = result.series.first().ok_or(Error::EmptySeries)?;
-                if let Series::TagSeries(series) = series {
-                    let iterator: std::slice::Iter<(String, String)> = series.values.iter();
-                    let values = iterator.map(|(_key, value)| value.clone()).collect();
-                    Ok(values)
-                } else {
-                    Err(Error::NotATagSeries)
-                }
-            }
-        }
-    }
+/// A time-indexed row of typed values
+type TimeRow = (DateTime<Utc>, Vec<Value>);
+
+/// Convert rows to time-indexed rows
+fn time_rows(rows: Vec<Row>) -> Result<Vec<TimeRow>, Error> {
+    rows.into_iter()
+        .map(|row| match row.key {
+            RowKey::Time(instant) => Ok((instant, row.values)),
+            RowKey::Text(_) => Err(Error::NotATimeSeries),
+        })
+        .collect()
 }
 
-/// An InfluxDB result
-#[derive(Debug, Deserialize, Clone)]
-#[serde(untagged)]
-enum InfluxDBResult {
-    /// An error result
-    Error(InfluxDBErrorResult),
-
-    /// A success result
-    Success(InfluxDBSuccessResult),
+/// Convert first-column rows to time-series points
+fn first_column_rows<T>(rows: Vec<FirstColumnRow<T>>) -> Result<Vec<(DateTime<Utc>, T)>, Error> {
+    rows.into_iter()
+        .map(|row| match row.key {
+            RowKey::Time(instant) => Ok((instant, row.value)),
+            RowKey::Text(_) => Err(Error::NotATimeSeries),
+        })
+        .collect()
 }
 
-/// Result for an InfluxDB query
+/// An InfluxDB result
 #[derive(Debug, Deserialize, Clone)]
-#[allow(unused)]
-struct InfluxDBSuccessResult {
+#[serde(bound = "R: Deserialize<'de>")]
+struct InfluxDBResult<R> {
     /// Statement ID
     statement_id: u32,
 
+    /// Error message
+    error: Option<String>,
+
     /// Resulting series
     #[serde(default = "Vec::new")]
-    series: Vec<Series>,
+    series: Vec<Series<R>>,
 }
 
-/// Result for an InfluxDB query
-#[derive(Debug, Deserialize, Clone)]
-#[allow(unused)]
-struct InfluxDBErrorResult {
-    /// Statement ID
-    statement_id: u32,
-
-    /// Error message
-    error: String,
-}
-
-/// A time-series within an InfluxDB result
+/// A series within an InfluxDB result
 #[derive(Debug, Deserialize, Clone)]
-#[allow(unused)]
-struct TimeSeries {
+#[serde(bound = "R: Deserialize<'de>")]
+pub struct Series<R = Row> {
     /// Series name
     name: String,
 
@@ -224,36 +351,311 @@ struct TimeSeries {
     columns: Vec<String>,
 
     /// Series values
-    values: Vec<(DateTime<Utc>, JsonValue)>,
+    #[serde(default = "Vec::new")]
+    values: Vec<R>,
 
     /// Series tags
     tags: Option<HashMap<String, String>>,
+
+    /// Flag for series split across multiple chunks
+    #[serde(default)]
+    partial: bool,
 }
 
-/// A series of tags within an InfluxDB result
-#[derive(Debug, Deserialize, Clone)]
-#[allow(unused)]
-struct TagSeries {
-    /// Series name
-    name: String,
+/// A row within an InfluxDB series
+///
+/// The first column is either the time of a time-series or the key of a
+/// series of tags, and the remaining columns are values.
+#[derive(Debug, Clone)]
+pub struct Row {
+    /// First column
+    key: RowKey,
 
-    /// Series columns
-    columns: Vec<String>,
+    /// Remaining columns
+    values: Vec<Value>,
+}
 
-    /// Series values
-    values: Vec<(String, String)>,
+impl<'de> Deserialize<'de> for Row {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(RowVisitor)
+    }
+}
 
-    /// Series tags
-    tags: Option<HashMap<String, String>>,
+/// Visitor for deserializing a row directly from a JSON array
+struct RowVisitor;
+
+impl<'de> Visitor<'de> for RowVisitor {
+    type Value = Row;
+
+    fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("a non-empty array of values")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let key: RowKey = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(1));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Row { key, values })
+    }
 }
 
-/// A series within an InfluxDB result
-#[derive(Debug, Deserialize, Clone)]
-#[serde(untagged)]
-enum Series {
-    /// A time-series
-    TimeSeries(TimeSeries),
+/// A row within an InfluxDB series, keeping only its first value
+///
+/// Remaining values are skipped while deserializing, so that no row of
+/// typed values is allocated.
+#[derive(Debug, Clone)]
+pub struct FirstColumnRow<T> {
+    /// First column
+    key: RowKey,
+
+    /// First value
+    value: T,
+}
+
+/// A type that can be converted from the first value of a row
+pub trait FromValue: Sized {
+    /// Convert a value, or its absence
+    fn from_value(value: Option<Value>) -> Self;
+}
+
+impl FromValue for f64 {
+    /// Non-numeric values are converted to `NaN`
+    fn from_value(value: Option<Value>) -> Self {
+        value.as_ref().and_then(Value::as_f64).unwrap_or(f64::NAN)
+    }
+}
+
+impl FromValue for String {
+    /// Non-string values are converted to empty strings
+    fn from_value(value: Option<Value>) -> Self {
+        match value {
+            Some(Value::String(value)) => value,
+            _ => String::new(),
+        }
+    }
+}
+
+impl<'de, T: FromValue> Deserialize<'de> for FirstColumnRow<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(FirstColumnRowVisitor(PhantomData))
+    }
+}
+
+/// Visitor for deserializing the first value of a row
+struct FirstColumnRowVisitor<T>(PhantomData<T>);
+
+impl<'de, T: FromValue> Visitor<'de> for FirstColumnRowVisitor<T> {
+    type Value = FirstColumnRow<T>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("a non-empty array of values")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let key: RowKey = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let value = T::from_value(seq.next_element()?);
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(FirstColumnRow { key, value })
+    }
+}
+
+/// First column of a row
+#[derive(Debug, Clone)]
+pub enum RowKey {
+    /// Time of a time-series point
+    Time(DateTime<Utc>),
+
+    /// Key of a series of tags
+    #[allow(unused)]
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for RowKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(RowKeyVisitor)
+    }
+}
+
+/// Visitor for deserializing the first column of a row
+struct RowKeyVisitor;
+
+impl Visitor<'_> for RowKeyVisitor {
+    type Value = RowKey;
+
+    fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("a timestamp or a tag key")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        Ok(DateTime::parse_from_rfc3339(value).map_or_else(
+            |_| RowKey::Text(value.to_owned()),
+            |instant| RowKey::Time(instant.with_timezone(&Utc)),
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Visitor for deserializing a value within a row
+///
+/// Values are deserialized directly from the response, without going
+/// through an intermediate generic JSON value.
+struct ValueVisitor;
+
+impl Visitor<'_> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("a number, a boolean, a string or null")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Value::Boolean(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Value::Integer(value))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(i64::try_from(value).map_or(Value::Float(value as f64), Value::Integer))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
 
-    /// A series of tags
-    TagSeries(TagSeries),
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<S: SeriesSink>(text: &str, sink: S) -> Result<S::Output, Error> {
+        let mut builder = ResponseBuilder::new(sink);
+        builder.push(InfluxDBResponse::try_from(text)?)?;
+        builder.finish()
+    }
+
+    #[test]
+    fn parse_typed_multi_field_frames() {
+        let text = r#"{"results":[{"statement_id":0,"series":[{"name":"system","tags":{"host":"pi","rack":"a"},"columns":["time","load","uptime","online","status","missing"],"values":[["2023-12-01T00:00:00Z",0.25,3600,true,"running",null]]}]}]}"#;
+        let frames: Vec<Frame> = parse(text, FrameSink::default()).unwrap_or_default();
+
+        assert_eq!(frames.len(), 1);
+        let frame = &frames[0];
+        assert_eq!(frame.name(), "system");
+        assert_eq!(frame.tag("host"), Some("pi"));
+        assert_eq!(frame.tag("rack"), Some("a"));
+        assert_eq!(
+            frame.columns(),
+            &["load", "uptime", "online", "status", "missing"]
+        );
+        assert_eq!(
+            frame.rows()[0].1,
+            vec![
+                Value::Float(0.25),
+                Value::Integer(3600),
+                Value::Boolean(true),
+                Value::String("running".to_owned()),
+                Value::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_first_column_only() {
+        let text = r#"{"results":[{"statement_id":0,"series":[{"name":"system","tags":{"host":"pi"},"columns":["time","status","load"],"values":[["2023-12-01T00:00:00Z","running",0.25],["2023-12-01T01:00:00Z",1,{"nested":[1,2]}]]}]}]}"#;
+        let label = LabelTemplate::for_tag("host");
+
+        let strings = parse(text, TaggedSink::<String>::new(&label)).unwrap_or_default();
+        let values: Vec<&str> = strings["pi"]
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(values, vec!["running", ""]);
+
+        let floats = parse(text, TaggedSink::<f64>::new(&label)).unwrap_or_default();
+        assert!(floats["pi"][0].1.is_nan());
+        assert!((floats["pi"][1].1 - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn reject_duplicate_labels() {
+        let text = r#"{"results":[{"statement_id":0,"series":[{"name":"system","tags":{"host":"pi","rack":"a"},"columns":["time","load"],"values":[["2023-12-01T00:00:00Z",0.25]]},{"name":"system","tags":{"host":"pi","rack":"b"},"columns":["time","load"],"values":[["2023-12-01T00:00:00Z",0.5]]}]}]}"#;
+
+        let label = LabelTemplate::for_tag("host");
+        let seriess = parse(text, TaggedSink::<f64>::new(&label));
+        assert!(matches!(seriess, Err(Error::DuplicateLabel(ref label)) if label == "pi"));
+
+        let label = LabelTemplate::for_tag("rack");
+        let seriess = parse(text, TaggedSink::<f64>::new(&label));
+        assert_eq!(seriess.ok().map(|seriess| seriess.len()), Some(2));
+    }
+
+    #[test]
+    fn parse_tag_values() {
+        let text = r#"{"results":[{"statement_id":0,"series":[{"name":"system","columns":["key","value"],"values":[["host","pi"],["host","nas"]]}]}]}"#;
+        let tags = parse(text, TagValuesSink::default()).unwrap_or_default();
+        assert_eq!(tags.len(), 2);
+        assert!(tags.contains("nas"));
+
+        let text = r#"{"results":[{"statement_id":0}]}"#;
+        let tags = parse(text, TagValuesSink::default());
+        assert!(matches!(tags, Err(Error::EmptySeries)));
+    }
+
+    #[test]
+    fn parse_error_result() {
+        let text = r#"{"results":[{"statement_id":0,"error":"database not found: house"}]}"#;
+        let frames = parse(text, FrameSink::default());
+        assert!(matches!(frames, Err(Error::InfluxDBError(_))));
+    }
 }
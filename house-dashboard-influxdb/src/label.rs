@@ -0,0 +1,319 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for grouping series by tags and labelling them
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::{Error, Frame};
+
+/// One or more tag keys to group series by
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum TagKeys {
+    /// A single tag key
+    Single(String),
+
+    /// A list of tag keys
+    Multiple(Vec<String>),
+}
+
+impl TagKeys {
+    /// Return the tag keys as a slice
+    #[must_use]
+    pub fn keys(&self) -> &[String] {
+        match *self {
+            TagKeys::Single(ref key) => std::slice::from_ref(key),
+            TagKeys::Multiple(ref keys) => keys,
+        }
+    }
+
+    /// Return the tag keys identifying series by a tag and naming them by a label
+    ///
+    /// The tag comes first, followed by the other tags the label refers to.
+    #[must_use]
+    pub fn for_tag_and_label(tag: &str, label: &LabelTemplate) -> Self {
+        let mut keys = vec![tag.to_owned()];
+        keys.extend(label.tag_names().into_iter().filter(|name| name != tag));
+        TagKeys::Multiple(keys)
+    }
+
+    /// Return the tag keys formatted for a `GROUP BY` clause
+    ///
+    /// Keys are quoted, so they can contain any character.
+    #[must_use]
+    pub fn to_group_by(&self) -> String {
+        self.keys()
+            .iter()
+            .map(|key| quote_identifier(key))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Return the default label template for these tag keys
+    ///
+    /// The default template contains all tag values separated by spaces.
+    #[must_use]
+    pub fn default_label(&self) -> LabelTemplate {
+        let template = self
+            .keys()
+            .iter()
+            .map(|key| format!("{{{key}}}"))
+            .collect::<Vec<String>>()
+            .join(" ");
+        LabelTemplate::new(template)
+    }
+}
+
+/// Quote an identifier for an InfluxDB query
+fn quote_identifier(identifier: &str) -> String {
+    let escaped = identifier.replace('\\', r"\\").replace('"', r#"\""#);
+    format!("\"{escaped}\"")
+}
+
+/// Return the last value of each frame identified by a tag, by label
+///
+/// Frames without the tag are skipped.
+/// The labels of the frames are also recorded by the value of their tag,
+/// including labels of frames without a last value.
+///
+/// # Errors
+///
+/// Return an error when the label refers to a missing tag, or when multiple
+/// frames have the same label.
+#[allow(clippy::implicit_hasher)]
+pub fn last_values_by_label<T>(
+    frames: Vec<Frame>,
+    (tag, label): (&str, &LabelTemplate),
+    names: &mut HashMap<String, Vec<String>>,
+    last: impl Fn(&Frame) -> Option<T>,
+) -> Result<HashMap<String, T>, Error> {
+    let mut labels = HashSet::new();
+    let mut values = HashMap::new();
+    for frame in frames {
+        let Some(tag_value) = frame.tag(tag) else {
+            continue;
+        };
+        let name = label.render(frame.tags())?;
+        if !labels.insert(name.clone()) {
+            return Err(Error::DuplicateLabel(name));
+        }
+        if let Some(value) = last(&frame) {
+            values.insert(name.clone(), value);
+        }
+        let tag_names = names.entry(tag_value.to_owned()).or_default();
+        if !tag_names.contains(&name) {
+            tag_names.push(name);
+        }
+    }
+    Ok(values)
+}
+
+/// A template for composing series labels from tag values
+///
+/// Tag names are enclosed in braces, e.g. `"{room} ({sensor})"`.
+/// Literal braces can be written as `{{` and `}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct LabelTemplate(String);
+
+impl LabelTemplate {
+    /// Create a new label template
+    #[must_use]
+    pub fn new(template: impl Into<String>) -> Self {
+        Self(template.into())
+    }
+
+    /// Create a label template for a single tag
+    #[must_use]
+    pub fn for_tag(tag_name: &str) -> Self {
+        Self(format!("{{{tag_name}}}"))
+    }
+
+    /// Render the label from a set of tag values
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the template refers to a missing tag.
+    pub fn render(&self, tags: &HashMap<String, String>) -> Result<String, Error> {
+        self.expand(|name, label| {
+            let value = tags
+                .get(name)
+                .ok_or_else(|| Error::MissingTag(name.to_owned()))?;
+            label.push_str(value);
+            Ok(())
+        })
+    }
+
+    /// Return the names of the tags the template refers to
+    ///
+    /// Names are returned in order of first appearance, without duplicates.
+    #[must_use]
+    pub fn tag_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        self.expand(|name, _label| {
+            if !names.iter().any(|existing| existing == name) {
+                names.push(name.to_owned());
+            }
+            Ok(())
+        })
+        .unwrap_or_default();
+        names
+    }
+
+    /// Expand the template, replacing each tag with a callback
+    fn expand(
+        &self,
+        mut replace: impl FnMut(&str, &mut String) -> Result<(), Error>,
+    ) -> Result<String, Error> {
+        let mut label = String::with_capacity(self.0.len());
+        let mut chars = self.0.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    label.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    label.push('}');
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    replace(&name, &mut label)?;
+                }
+                c => label.push(c),
+            }
+        }
+
+        Ok(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{DateTime, Utc};
+
+    use crate::Value;
+
+    fn tags() -> HashMap<String, String> {
+        HashMap::from([
+            ("room".to_owned(), "Kitchen".to_owned()),
+            ("sensor".to_owned(), "ceiling".to_owned()),
+        ])
+    }
+
+    #[test]
+    fn render_multiple_tags() {
+        let template = LabelTemplate::new("{room} ({sensor})");
+        let label = template.render(&tags()).ok();
+        assert_eq!(label.as_deref(), Some("Kitchen (ceiling)"));
+    }
+
+    #[test]
+    fn render_escaped_braces() {
+        let template = LabelTemplate::new("{{{room}}}");
+        let label = template.render(&tags()).ok();
+        assert_eq!(label.as_deref(), Some("{Kitchen}"));
+    }
+
+    #[test]
+    fn render_missing_tag() {
+        let template = LabelTemplate::new("{room} {floor}");
+        let label = template.render(&tags());
+        assert!(matches!(label, Err(Error::MissingTag(name)) if name == "floor"));
+    }
+
+    #[test]
+    fn tag_names_without_duplicates() {
+        let template = LabelTemplate::new("{{literal}} {host} ({rack}, {host})");
+        assert_eq!(template.tag_names(), vec!["host", "rack"]);
+    }
+
+    #[test]
+    fn default_label_for_multiple_keys() {
+        let keys = TagKeys::Multiple(vec!["room".to_owned(), "sensor".to_owned()]);
+        assert_eq!(keys.to_group_by(), r#""room","sensor""#);
+        assert_eq!(keys.default_label(), LabelTemplate::new("{room} {sensor}"));
+    }
+
+    #[test]
+    fn quote_tag_keys() {
+        let keys = TagKeys::Multiple(vec![
+            "room name".to_owned(),
+            "sensor-id".to_owned(),
+            "time".to_owned(),
+            r#"say "hi"\"#.to_owned(),
+        ]);
+        let expected = r#""room name","sensor-id","time","say \"hi\"\\""#;
+        assert_eq!(keys.to_group_by(), expected);
+    }
+
+    #[test]
+    fn tag_keys_for_tag_and_label() {
+        let label = LabelTemplate::new("{host} ({rack})");
+        let keys = TagKeys::for_tag_and_label("host", &label);
+        assert_eq!(keys.keys(), ["host", "rack"]);
+    }
+
+    /// Create a frame for a host and a rack, with an optional value
+    fn host_frame(host: &str, rack: &str, value: Option<f64>) -> Frame {
+        let tags = HashMap::from([
+            ("host".to_owned(), host.to_owned()),
+            ("rack".to_owned(), rack.to_owned()),
+        ]);
+        let rows = value
+            .into_iter()
+            .map(|value| (DateTime::<Utc>::default(), vec![Value::Float(value)]))
+            .collect();
+        Frame::new("system".to_owned(), tags, vec!["load".to_owned()], rows)
+    }
+
+    #[test]
+    fn last_values_with_names() {
+        let frames = vec![
+            host_frame("pi", "a", Some(0.5)),
+            host_frame("pi", "b", None),
+            host_frame("nas", "a", Some(0.25)),
+        ];
+        let label = LabelTemplate::new("{host} ({rack})");
+        let mut names = HashMap::new();
+        let values = last_values_by_label(frames, ("host", &label), &mut names, |frame| {
+            frame
+                .first_float_column()
+                .last()
+                .map(|&(_instant, value)| value)
+        });
+
+        let expected = HashMap::from([("pi (a)".to_owned(), 0.5), ("nas (a)".to_owned(), 0.25)]);
+        assert_eq!(values.ok(), Some(expected));
+        assert_eq!(
+            names.get("pi"),
+            Some(&vec!["pi (a)".to_owned(), "pi (b)".to_owned()])
+        );
+    }
+
+    #[test]
+    fn reject_duplicate_labels_without_values() {
+        let frames = vec![
+            host_frame("pi", "a", None),
+            host_frame("pi", "b", Some(0.5)),
+        ];
+        let label = LabelTemplate::for_tag("host");
+        let values = last_values_by_label(frames, ("host", &label), &mut HashMap::new(), |frame| {
+            frame
+                .first_float_column()
+                .last()
+                .map(|&(_instant, value)| value)
+        });
+        assert!(matches!(values, Err(Error::DuplicateLabel(name)) if name == "pi"));
+    }
+}
@@ -7,6 +7,8 @@
 //! Data types and functions for fetching data from InfluxDB
 
 use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::time::Instant;
 
 use async_std::io::ReadExt as _;
 
@@ -22,11 +24,26 @@
 
 use serde_html_form::to_string as to_form_urlencoded;
 
+mod chunked;
+use chunked::ChunkedResponseParser;
+
 mod error;
 pub use error::Error;
 
+mod frame;
+pub use frame::{Frame, Value};
+
+mod label;
+pub use label::{last_values_by_label, LabelTemplate, TagKeys};
+
 mod influxql;
-use influxql::{InfluxDBResponse, TaggedDataFrame, TaggedStringDataFrame};
+use influxql::{FrameSink, SeriesSink, TagValuesSink, TaggedSink};
+
+mod line_protocol;
+pub use line_protocol::Point;
+
+mod statistics;
+pub use statistics::QueryStatistics;
 
 /// A client to InfluxDB
 #[derive(Clone, Debug)]
@@ -36,8 +53,26 @@ pub struct InfluxDBClient {
 
     /// The underlying HTTP client
     http_client: Arc<Mutex<HttpClient>>,
+
+    /// Number of points per chunk in InfluxDB responses
+    chunk_size: NonZeroUsize,
+
+    /// Name of the chart issuing queries, used for error reporting
+    chart_name: Option<String>,
+
+    /// Statistics about the queries sent by this client
+    statistics: Arc<Mutex<QueryStatistics>>,
 }
 
+/// Default number of points per chunk in InfluxDB responses
+pub const DEFAULT_CHUNK_SIZE: NonZeroUsize = match NonZeroUsize::new(10_000) {
+    Some(chunk_size) => chunk_size,
+    None => NonZeroUsize::MIN,
+};
+
+/// Size of the buffer used for reading responses
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
 impl InfluxDBClient {
     /// Create a new InfluxDB client from an HTTP client
     #[must_use]
@@ -46,7 +81,87 @@ pub fn new(base_url: Url, http_client: HttpClient) -> Self {
         Self {
             base_url,
             http_client,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chart_name: None,
+            statistics: Arc::new(Mutex::new(QueryStatistics::default())),
+        }
+    }
+
+    /// Set the number of points per chunk in InfluxDB responses
+    #[must_use]
+    pub fn with_chunk_size(self, chunk_size: NonZeroUsize) -> Self {
+        Self { chunk_size, ..self }
+    }
+
+    /// Set the name of the chart issuing queries
+    ///
+    /// The chart name is included in errors.
+    /// The returned client tracks its own query statistics, separately from
+    /// the original client.
+    #[must_use]
+    pub fn with_chart_name(self, chart_name: &str) -> Self {
+        Self {
+            chart_name: Some(chart_name.to_owned()),
+            statistics: Arc::new(Mutex::new(QueryStatistics::default())),
+            ..self
+        }
+    }
+
+    /// Return statistics about the queries sent by this client
+    pub async fn statistics(&self) -> QueryStatistics {
+        *self.statistics.lock().await
+    }
+
+    /// Write points to a database using line protocol
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the HTTP connection fails, or when InfluxDB
+    /// rejects the points.
+    pub async fn write(&self, database: &str, points: &[Point]) -> Result<(), Error> {
+        let description = format!("write {} points to database {database}", points.len());
+        self.write_inner(database, points)
+            .await
+            .map_err(|error| self.query_error(error, &description))
+    }
+
+    /// Write points to a database without adding context to errors
+    async fn write_inner(&self, database: &str, points: &[Point]) -> Result<(), Error> {
+        let body = points
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        trace!("Request body: {}", body);
+
+        let mut url = self.base_url.join("/write")?;
+        url.query_pairs_mut()
+            .append_pair("db", database)
+            .append_pair("precision", "ns");
+
+        let request = Request::post(url.to_string())
+            .header("Content-type", "text/plain; charset=utf-8")
+            .body(body)?;
+
+        let http_client = self.http_client.lock().await;
+
+        let mut response = http_client.send_async(request).await?;
+
+        let status = response.status();
+        debug!("Response status: {:?}", status);
+
+        if !status.is_success() {
+            let mut buffer = Vec::new();
+            response
+                .body_mut()
+                .take(READ_BUFFER_SIZE as u64)
+                .read_to_end(&mut buffer)
+                .await?;
+            return Err(Error::from_http_status(status, &buffer));
         }
+
+        Ok(())
     }
 
     /// Fetch existing tag values for a given key in a measurement
@@ -66,13 +181,38 @@ pub async fn fetch_tag_values(
         let query = format!(
             r#"SHOW TAG VALUES ON "{database}" FROM {measurement} WITH KEY = "{key}" WHERE "{filter_tag_name}" = '{filter_tag_value}'"#
         );
-        let results = self.request(&query).await?;
-        let tags: HashSet<String> = (&results).try_into()?;
+        let tags = self
+            .request(&query, TagValuesSink::default())
+            .await
+            .map_err(|error| self.query_error(error, &query))?;
         debug!("Fetched {} tags", tags.len());
         Ok(tags)
     }
 
-    /// Fetch a list of named time-series, one per each tag value
+    /// Fetch a list of frames, one per each combination of tag values
+    ///
+    /// Each frame contains all columns returned by the query, with typed
+    /// values.
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the HTTP connection fails, when the InfluxDB
+    /// connection fails, or when the response cannot be parsed.
+    pub async fn fetch_frames(&self, query: &str) -> Result<Vec<Frame>, Error> {
+        let frames = self
+            .request(query, FrameSink::default())
+            .await
+            .map_err(|error| self.query_error(error, query))?;
+
+        debug!("Fetched {} frames", frames.len());
+
+        Ok(frames)
+    }
+
+    /// Fetch a list of named time-series, one per each combination of tag values
+    ///
+    /// Each time-series is named by rendering the label template with its
+    /// tag values.
     ///
     /// # Errors
     ///
@@ -81,20 +221,22 @@ pub async fn fetch_tag_values(
     pub async fn fetch_tagged_dataframes(
         &self,
         query: &str,
-        tag_name: &str,
+        label: &LabelTemplate,
     ) -> Result<HashMap<String, Vec<(DateTime<Utc>, f64)>>, Error> {
-        let results = self.request(query).await?;
-
-        let a: (&str, &InfluxDBResponse) = (tag_name, &results);
-        let dataframe: TaggedDataFrame = a.try_into()?;
-        let seriess: HashMap<String, Vec<(DateTime<Utc>, f64)>> = dataframe.into();
+        let seriess = self
+            .request(query, TaggedSink::<f64>::new(label))
+            .await
+            .map_err(|error| self.query_error(error, query))?;
 
         debug!("Fetched {} time-series", seriess.len());
 
         Ok(seriess)
     }
 
-    /// Fetch a list of named string time-series, one per each tag value
+    /// Fetch a list of named string time-series, one per each combination of tag values
+    ///
+    /// Each time-series is named by rendering the label template with its
+    /// tag values.
     ///
     /// # Errors
     ///
@@ -103,22 +245,57 @@ pub async fn fetch_tagged_dataframes(
     pub async fn fetch_tagged_string_dataframes(
         &self,
         query: &str,
-        tag_name: &str,
+        label: &LabelTemplate,
     ) -> Result<HashMap<String, Vec<(DateTime<Utc>, String)>>, Error> {
-        let results = self.request(query).await?;
-
-        let a: (&str, &InfluxDBResponse) = (tag_name, &results);
-        let dataframe: TaggedStringDataFrame = a.try_into()?;
-        let seriess: HashMap<String, Vec<(DateTime<Utc>, String)>> = dataframe.into();
+        let seriess = self
+            .request(query, TaggedSink::<String>::new(label))
+            .await
+            .map_err(|error| self.query_error(error, query))?;
 
         debug!("Fetched {} time-series", seriess.len());
 
         Ok(seriess)
     }
 
-    /// Send a request to InfluxDB server and parse its response
-    async fn request(&self, query: &str) -> Result<InfluxDBResponse, Error> {
-        let params = [("q", query)];
+    /// Add the query context to an error
+    fn query_error(&self, error: Error, query: &str) -> Error {
+        error.with_query_context(self.chart_name.as_deref(), query)
+    }
+
+    /// Send a request to InfluxDB server and feed its response to a sink
+    ///
+    /// Query latency, response size and failures are recorded in the client
+    /// statistics.
+    async fn request<S: SeriesSink>(&self, query: &str, sink: S) -> Result<S::Output, Error> {
+        let start = Instant::now();
+        let mut bytes = 0;
+
+        let result = self.send_query(query, sink, &mut bytes).await;
+
+        self.statistics
+            .lock()
+            .await
+            .record(start.elapsed(), bytes, result.is_ok());
+
+        result
+    }
+
+    /// Send a query to InfluxDB server and feed its response to a sink
+    ///
+    /// The response is requested in chunks, which are parsed and fed to the
+    /// sink as soon as they are received.
+    async fn send_query<S: SeriesSink>(
+        &self,
+        query: &str,
+        sink: S,
+        bytes: &mut u64,
+    ) -> Result<S::Output, Error> {
+        let chunk_size = self.chunk_size.to_string();
+        let params = [
+            ("q", query),
+            ("chunked", "true"),
+            ("chunk_size", chunk_size.as_str()),
+        ];
         let body = to_form_urlencoded(params)?;
 
         trace!("Request body: {}", body);
@@ -134,19 +311,34 @@ async fn request(&self, query: &str) -> Result<InfluxDBResponse, Error> {
 
         let mut response = http_client.send_async(request).await?;
 
-        debug!("Response status: {:?}", response.status());
+        let status = response.status();
+        debug!("Response status: {:?}", status);
 
         let body = response.body_mut();
 
-        let mut buffer = Vec::new();
-        body.read_to_end(&mut buffer).await?;
-
-        trace!("Response text: {}", String::from_utf8_lossy(&buffer));
-
-        let influxdb_results: InfluxDBResponse = buffer.as_slice().try_into()?;
+        if !status.is_success() {
+            let mut buffer = Vec::new();
+            body.take(READ_BUFFER_SIZE as u64)
+                .read_to_end(&mut buffer)
+                .await?;
+            return Err(Error::from_http_status(status, &buffer));
+        }
 
-        trace!("Parsed response: {:?}", influxdb_results);
+        let mut parser = ChunkedResponseParser::new(sink);
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        loop {
+            let length = body.read(&mut buffer).await?;
+            if length == 0 {
+                break;
+            }
+            *bytes += length as u64;
+            trace!(
+                "Response text: {}",
+                String::from_utf8_lossy(&buffer[..length])
+            );
+            parser.feed(&buffer[..length])?;
+        }
 
-        Ok(influxdb_results)
+        parser.finish()
     }
 }
@@ -0,0 +1,148 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for writing points in InfluxDB line protocol
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use chrono::{DateTime, Utc};
+
+use crate::frame::Value;
+
+/// A point to be written to InfluxDB
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    /// Measurement name
+    measurement: String,
+
+    /// Tag keys and values
+    tags: Vec<(String, String)>,
+
+    /// Field keys and values
+    fields: Vec<(String, Value)>,
+
+    /// Point time, or server time if missing
+    timestamp: Option<DateTime<Utc>>,
+}
+
+impl Point {
+    /// Create a new point without tags and fields
+    #[must_use]
+    pub fn new(measurement: &str) -> Self {
+        Self {
+            measurement: measurement.to_owned(),
+            tags: Vec::new(),
+            fields: Vec::new(),
+            timestamp: None,
+        }
+    }
+
+    /// Add a tag to the point
+    #[must_use]
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Add a field to the point
+    #[must_use]
+    pub fn with_field(mut self, key: &str, value: Value) -> Self {
+        self.fields.push((key.to_owned(), value));
+        self
+    }
+
+    /// Set the point time
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl Display for Point {
+    /// Format the point as a line in InfluxDB line protocol
+    ///
+    /// Null fields are omitted, since line protocol has no representation
+    /// for them.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", escape(&self.measurement, &[',', ' ']))?;
+
+        for (key, value) in &self.tags {
+            write!(
+                f,
+                ",{}={}",
+                escape(key, &[',', '=', ' ']),
+                escape(value, &[',', '=', ' '])
+            )?;
+        }
+
+        let mut separator = ' ';
+        for (key, value) in &self.fields {
+            let value = match *value {
+                Value::Float(value) => format!("{value:?}"),
+                Value::Integer(value) => format!("{value}i"),
+                Value::Boolean(value) => format!("{value}"),
+                Value::String(ref value) => format!("\"{}\"", escape(value, &['"', '\\'])),
+                Value::Null => continue,
+            };
+            write!(f, "{separator}{}={value}", escape(key, &[',', '=', ' ']))?;
+            separator = ',';
+        }
+
+        if let Some(timestamp) = self.timestamp.and_then(|t| t.timestamp_nanos_opt()) {
+            write!(f, " {timestamp}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape special characters with backslashes
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn format_point() {
+        let timestamp = Utc
+            .with_ymd_and_hms(2023, 12, 1, 0, 0, 0)
+            .single()
+            .unwrap_or_default();
+        let point = Point::new("dashboard")
+            .with_tag("chart", "Indoor temperature")
+            .with_field("render_time", Value::Float(0.5))
+            .with_field("attempts", Value::Integer(1))
+            .with_field("success", Value::Boolean(true))
+            .with_field("kind", Value::String("Trend \"line\"".to_owned()))
+            .with_field("missing", Value::Null)
+            .with_timestamp(timestamp);
+        let expected = concat!(
+            r"dashboard,chart=Indoor\ temperature ",
+            r#"render_time=0.5,attempts=1i,success=true,kind="Trend \"line\"" "#,
+            "1701388800000000000",
+        );
+        assert_eq!(point.to_string(), expected);
+    }
+
+    #[test]
+    fn format_point_without_timestamp() {
+        let point = Point::new("dash board").with_field("value", Value::Float(1.0));
+        assert_eq!(point.to_string(), r"dash\ board value=1.0");
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for tracking query statistics
+
+use std::time::Duration;
+
+/// Statistics about the queries sent by a client
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueryStatistics {
+    /// Number of queries sent
+    pub queries: u64,
+
+    /// Number of failed queries
+    pub failures: u64,
+
+    /// Total time spent waiting for and reading responses
+    pub latency: Duration,
+
+    /// Total number of response bytes fetched
+    pub bytes: u64,
+}
+
+impl QueryStatistics {
+    /// Record a query
+    pub fn record(&mut self, latency: Duration, bytes: u64, success: bool) {
+        self.queries += 1;
+        if !success {
+            self.failures += 1;
+        }
+        self.latency += latency;
+        self.bytes += bytes;
+    }
+}
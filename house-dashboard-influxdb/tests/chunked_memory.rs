@@ -0,0 +1,197 @@
+// Copyright Claudio Mattera 2023.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Memory usage of chunked InfluxDB responses
+//!
+//! This test replaces the global allocator, so it is the only test in its
+//! binary.
+
+#![allow(clippy::panic_in_result_fn)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use chrono::{DateTime, Utc};
+
+use isahc::HttpClient;
+
+use url::Url;
+
+use house_dashboard_influxdb::{Frame, InfluxDBClient, LabelTemplate};
+
+thread_local! {
+    /// Bytes currently allocated by this thread
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+
+    /// Highest number of bytes allocated by this thread
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+/// An allocator tracking the heap usage of each thread
+struct TrackingAllocator;
+
+impl TrackingAllocator {
+    /// Record an allocation of the current thread
+    fn grow(size: usize) {
+        ALLOCATED
+            .try_with(|allocated| {
+                allocated.set(allocated.get() + size);
+                PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())))
+                    .unwrap_or_default();
+            })
+            .unwrap_or_default();
+    }
+
+    /// Record a deallocation of the current thread
+    fn shrink(size: usize) {
+        ALLOCATED
+            .try_with(|allocated| allocated.set(allocated.get().saturating_sub(size)))
+            .unwrap_or_default();
+    }
+}
+
+// SAFETY: All allocations are delegated to the system allocator.
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::grow(layout.size());
+        // SAFETY: The caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::shrink(layout.size());
+        // SAFETY: The caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::grow(new_size);
+        Self::shrink(layout.size());
+        // SAFETY: The caller upholds the contract of `GlobalAlloc::realloc`.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// Return the peak heap usage of a function above the current usage
+///
+/// Only allocations of the current thread are tracked, so the HTTP client
+/// and the server threads are not included.
+fn peak_heap_usage<T>(function: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    let output = function();
+    (output, PEAK.with(Cell::get) - start)
+}
+
+/// Return a response of a single series split in chunks
+fn large_response(chunks: usize, chunk_size: usize) -> Result<String, Box<dyn Error>> {
+    let mut text = String::new();
+    for chunk in 0..chunks {
+        text.push_str(r#"{"results":[{"statement_id":0,"series":[{"name":"power","tags":{"meter":"main"},"columns":["time","mean"],"values":["#);
+        for point in 0..chunk_size {
+            let seconds = chunk * chunk_size + point;
+            let separator = if point == 0 { "" } else { "," };
+            write!(
+                text,
+                r#"{separator}["2023-12-01T{:02}:{:02}:{:02}Z",{}.5]"#,
+                seconds / 3600 % 24,
+                seconds / 60 % 60,
+                seconds % 60,
+                seconds % 1000,
+            )?;
+        }
+        let partial = chunk + 1 < chunks;
+        writeln!(text, r#"],"partial":{partial}}}],"partial":{partial}}}]}}"#)?;
+    }
+    Ok(text)
+}
+
+/// Serve the same response to a number of HTTP requests
+///
+/// Return the URL of the server.
+fn serve(response: String, requests: usize) -> Result<Url, Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+
+    thread::spawn(move || -> Result<(), Box<dyn Error + Send + Sync>> {
+        for stream in listener.incoming().take(requests) {
+            let mut reader = BufReader::new(stream?);
+
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line)? > 2 {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse()?;
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                response.len(),
+            )?;
+            stream.write_all(response.as_bytes())?;
+        }
+        Ok(())
+    });
+
+    Ok(url)
+}
+
+/// Compare the peak heap usage of streaming a response into time-series
+/// with collecting all rows into frames before converting them
+///
+/// Run with `--nocapture` to print the measurements.
+#[test]
+fn streaming_peak_heap_usage() -> Result<(), Box<dyn Error>> {
+    let (chunks, chunk_size) = (50, 1_000);
+    let url = serve(large_response(chunks, chunk_size)?, 2)?;
+    let client = InfluxDBClient::new(url, HttpClient::new()?);
+    let label = LabelTemplate::for_tag("meter");
+    let query = r#"SELECT mean(value) FROM "power" GROUP BY time(1s),"meter""#;
+
+    let (streamed, streaming_peak) = peak_heap_usage(|| {
+        async_std::task::block_on(client.fetch_tagged_dataframes(query, &label))
+    });
+
+    let (collected, collecting_peak) = peak_heap_usage(|| {
+        async_std::task::block_on(client.fetch_frames(query)).map(|frames| {
+            frames
+                .iter()
+                .map(Frame::first_float_column)
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let points = chunks * chunk_size;
+    let output_size = points * size_of::<(DateTime<Utc>, f64)>();
+    println!(
+        "{points} points ({output_size} bytes of output): streaming peak {streaming_peak} bytes, collecting peak {collecting_peak} bytes"
+    );
+
+    assert_eq!(streamed?.get("main").map(Vec::len), Some(points));
+    assert_eq!(collected?.first().map(Vec::len), Some(points));
+    // Streaming only keeps the output, whose capacity may be up to twice
+    // its length while growing, and a single chunk
+    assert!(streaming_peak < 3 * output_size);
+    assert!(streaming_peak * 3 < collecting_peak * 2);
+
+    Ok(())
+}
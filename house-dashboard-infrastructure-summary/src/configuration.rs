@@ -9,12 +9,18 @@
 use serde::Deserialize;
 
 use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::window::TimeWindow;
+
+use house_dashboard_influxdb::LabelTemplate;
 
 /// Chart configuration for infrastructure summary charts
 #[derive(Debug, Deserialize)]
 pub struct InfrastructureSummaryConfiguration {
     /// Time of data from now
-    pub how_long_ago: Iso8601Duration,
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
 
     /// Chart title
     pub title: String,
@@ -27,4 +33,28 @@ pub struct InfrastructureSummaryConfiguration {
 
     /// Vertical space between hostnames
     pub vertical_step: Option<i32>,
+
+    /// Tag identifying hosts
+    pub tag: Option<String>,
+
+    /// Template for host names, composed from tag values
+    pub label: Option<LabelTemplate>,
+}
+
+impl InfrastructureSummaryConfiguration {
+    /// Return the tag identifying hosts
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        self.tag.as_deref().unwrap_or("host")
+    }
+
+    /// Return the template for host names
+    ///
+    /// Hosts are named after the value of their tag by default.
+    #[must_use]
+    pub fn label(&self) -> LabelTemplate {
+        self.label
+            .clone()
+            .unwrap_or_else(|| LabelTemplate::for_tag(self.tag()))
+    }
 }
@@ -10,60 +10,61 @@
 
 use tracing::debug;
 
-use miette::{IntoDiagnostic, Report, WrapErr};
+use miette::{Report, WrapErr};
 
-use time::Duration;
-
-use house_dashboard_common::duration::duration_to_query;
-
-use house_dashboard_influxdb::Error as InfluxDBError;
-use house_dashboard_influxdb::InfluxDBClient;
+use house_dashboard_influxdb::{last_values_by_label, InfluxDBClient, LabelTemplate, TagKeys};
 
 /// Fetch data for infrastructure summary
 ///
+/// Hosts are identified by a tag, and named by rendering a label template
+/// with the tag values of their series.
+/// Hosts without data are named after the value of their tag.
+///
 /// # Errors
 ///
 /// Return and error when data could not be fetched
 pub async fn fetch_data(
     influxdb_client: &InfluxDBClient,
-    how_long_ago: &Duration,
+    (tag, label): (&str, &LabelTemplate),
+    (since, until): (&str, &str),
 ) -> Result<(HashSet<String>, HashMap<String, f64>), Report> {
     let hosts: HashSet<String> = influxdb_client
-        .fetch_tag_values("telegraf", "system", "host", "always-on", "true")
+        .fetch_tag_values("telegraf", "system", tag, "always-on", "true")
         .await
-        .into_diagnostic()
         .wrap_err("cannot fetch existing hosts")?;
 
     let query = format!(
         "SELECT last({load_field}) / last({n_cpus_field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago} AND \"{filter_tag_name}\" = '{filter_tag_value}'
-        GROUP BY {tag}",
+        WHERE time < {until} AND time > {since} AND \"{filter_tag_name}\" = '{filter_tag_value}'
+        GROUP BY {tags}",
         load_field = "load15",
         n_cpus_field = "n_cpus",
         database = "telegraf",
         measurement = "system",
-        tag = "host",
+        tags = TagKeys::for_tag_and_label(tag, label).to_group_by(),
         filter_tag_name = "always-on",
         filter_tag_value = "true",
-        how_long_ago = duration_to_query(how_long_ago).into_diagnostic()?,
     );
 
     debug!("Query: {}", query);
 
-    let loads = match influxdb_client
-        .fetch_tagged_dataframes(&query, "host")
+    let frames = influxdb_client
+        .fetch_frames(&query)
         .await
-    {
-        Ok(loads) => Ok(loads),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
-        other => other,
-    }
-    .into_diagnostic()
-    .wrap_err("cannot fetch loads for always-on hosts")?;
+        .wrap_err("cannot fetch loads for always-on hosts")?;
+
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let loads = last_values_by_label(frames, (tag, label), &mut names, |frame| {
+        frame
+            .first_float_column()
+            .last()
+            .map(|&(_instant, value)| value)
+    })
+    .wrap_err("cannot name hosts")?;
 
-    let loads: HashMap<String, f64> = loads
+    let hosts = hosts
         .into_iter()
-        .filter_map(|(name, series)| series.last().map(|&(_instant, ref value)| (name, *value)))
+        .flat_map(|host| names.remove(&host).unwrap_or_else(|| vec![host]))
         .collect();
 
     Ok((hosts, loads))
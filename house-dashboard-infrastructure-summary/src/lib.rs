@@ -17,6 +17,7 @@
 use plotters::backend::BitMapBackend;
 
 use house_dashboard_common::configuration::StyleConfiguration;
+use house_dashboard_common::window::query_bounds;
 
 use house_dashboard_influxdb::InfluxDBClient;
 
@@ -54,9 +55,16 @@ pub async fn process_infrastructure_summary(
 ) -> Result<Vec<u8>, Report> {
     let now = OffsetDateTime::now_utc();
 
+    let (since, until) = query_bounds(
+        infrastructure_summary_configuration.how_long_ago.as_ref(),
+        infrastructure_summary_configuration.time_window,
+    )?;
+
+    let label = infrastructure_summary_configuration.label();
     let (hosts, loads) = fetch_data(
         influxdb_client,
-        &infrastructure_summary_configuration.how_long_ago.duration,
+        (infrastructure_summary_configuration.tag(), &label),
+        (&since, &until),
     )
     .await
     .wrap_err("cannot fetch data for infrastructure summary")?;
@@ -10,24 +10,24 @@
 
 use tracing::debug;
 
-use miette::{IntoDiagnostic, Report, WrapErr};
+use miette::{Report, WrapErr};
 
-use time::Duration;
-
-use house_dashboard_common::duration::duration_to_query;
-
-use house_dashboard_influxdb::Error as InfluxDBError;
-use house_dashboard_influxdb::InfluxDBClient;
+use house_dashboard_influxdb::{last_values_by_label, InfluxDBClient, LabelTemplate, TagKeys};
 
 /// Fetch data for Proxmox summary
 ///
+/// Hosts are identified by a tag, and named by rendering a label template
+/// with the tag values of their series.
+/// Hosts without data are named after the value of their tag.
+///
 /// # Errors
 ///
 /// Return and error when data could not be fetched
 pub async fn fetch_data(
     influxdb_client: &InfluxDBClient,
     node_fqdn: &str,
-    how_long_ago: &Duration,
+    (tag, label): (&str, &LabelTemplate),
+    (since, until): (&str, &str),
 ) -> Result<
     (
         HashSet<String>,
@@ -37,75 +37,66 @@ pub async fn fetch_data(
     Report,
 > {
     let hosts: HashSet<String> = influxdb_client
-        .fetch_tag_values("telegraf", "proxmox", "vm_name", "node_fqdn", node_fqdn)
+        .fetch_tag_values("telegraf", "proxmox", tag, "node_fqdn", node_fqdn)
         .await
-        .into_diagnostic()
         .wrap_err("cannot fetch existing hosts")?;
 
+    let tags = TagKeys::for_tag_and_label(tag, label).to_group_by();
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+
     let load_query = format!(
         "SELECT last({field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago} AND \"{filter_tag_name}\" = '{filter_tag_value}'
-        GROUP BY {tag}",
+        WHERE time < {until} AND time > {since} AND \"{filter_tag_name}\" = '{filter_tag_value}'
+        GROUP BY {tags}",
         field = "cpuload",
         database = "telegraf",
         measurement = "proxmox",
-        tag = "vm_name",
         filter_tag_name = "node_fqdn",
         filter_tag_value = node_fqdn,
-        how_long_ago = duration_to_query(how_long_ago).into_diagnostic()?,
     );
 
     debug!("Query: {}", load_query);
 
-    let loads = match influxdb_client
-        .fetch_tagged_dataframes(&load_query, "vm_name")
+    let frames = influxdb_client
+        .fetch_frames(&load_query)
         .await
-    {
-        Ok(loads) => Ok(loads),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
-        other => other,
-    }
-    .into_diagnostic()
-    .wrap_err("cannot fetch loads for Proxmox VMs")?;
-
-    let loads: HashMap<String, f64> = loads
-        .into_iter()
-        .filter_map(|(name, series)| series.last().map(|&(_instant, ref value)| (name, *value)))
-        .collect();
+        .wrap_err("cannot fetch loads for Proxmox VMs")?;
+    let loads = last_values_by_label(frames, (tag, label), &mut names, |frame| {
+        frame
+            .first_float_column()
+            .last()
+            .map(|&(_instant, value)| value)
+    })
+    .wrap_err("cannot name Proxmox VMs")?;
 
     let status_query = format!(
         "SELECT last({field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago} AND \"{filter_tag_name}\" = '{filter_tag_value}'
-        GROUP BY {tag}",
+        WHERE time < {until} AND time > {since} AND \"{filter_tag_name}\" = '{filter_tag_value}'
+        GROUP BY {tags}",
         field = "status",
         database = "telegraf",
         measurement = "proxmox",
-        tag = "vm_name",
         filter_tag_name = "node_fqdn",
         filter_tag_value = node_fqdn,
-        how_long_ago = duration_to_query(how_long_ago).into_diagnostic()?,
     );
 
     debug!("Query: {}", status_query);
 
-    let statuses = match influxdb_client
-        .fetch_tagged_string_dataframes(&status_query, "vm_name")
+    let frames = influxdb_client
+        .fetch_frames(&status_query)
         .await
-    {
-        Ok(statuses) => Ok(statuses),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
-        other => other,
-    }
-    .into_diagnostic()
-    .wrap_err("cannot fetch status for Proxmox VMs")?;
-
-    let statuses: HashMap<String, String> = statuses
+        .wrap_err("cannot fetch status for Proxmox VMs")?;
+    let statuses = last_values_by_label(frames, (tag, label), &mut names, |frame| {
+        frame
+            .first_string_column()
+            .pop()
+            .map(|(_instant, value)| value)
+    })
+    .wrap_err("cannot name Proxmox VMs")?;
+
+    let hosts = hosts
         .into_iter()
-        .filter_map(|(name, series)| {
-            series
-                .last()
-                .map(|&(_instant, ref value)| (name, value.clone()))
-        })
+        .flat_map(|host| names.remove(&host).unwrap_or_else(|| vec![host]))
         .collect();
 
     Ok((hosts, statuses, loads))
@@ -15,6 +15,7 @@
 use house_dashboard_influxdb::InfluxDBClient;
 
 use house_dashboard_common::configuration::StyleConfiguration;
+use house_dashboard_common::window::query_bounds;
 use plotters::backend::BitMapBackend;
 
 mod chart;
@@ -45,10 +46,17 @@ pub async fn process_proxmox_summary(
     style_configuration: &StyleConfiguration,
     index: usize,
 ) -> Result<Vec<u8>, Report> {
+    let (since, until) = query_bounds(
+        proxmox_summary_configuration.how_long_ago.as_ref(),
+        proxmox_summary_configuration.time_window,
+    )?;
+
+    let label = proxmox_summary_configuration.label();
     let (hosts, statuses, loads) = fetch_data(
         influxdb_client,
         &proxmox_summary_configuration.node_fqdn,
-        &proxmox_summary_configuration.how_long_ago.duration,
+        (proxmox_summary_configuration.tag(), &label),
+        (&since, &until),
     )
     .await
     .wrap_err("cannot fetch data for Proxmox summary")?;
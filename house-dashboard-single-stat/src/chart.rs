@@ -0,0 +1,349 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Functions for generating chart
+
+use tracing::{debug, info, warn};
+
+use chrono::{DateTime, Utc};
+
+use plotters::{
+    backend::{BitMapBackend, DrawingBackend},
+    chart::ChartBuilder,
+    coord::Shift,
+    drawing::{DrawingArea, IntoDrawingArea},
+    element::{Polygon, Rectangle, Text},
+    series::LineSeries,
+    style::{
+        text_anchor::{HPos, Pos, VPos},
+        Color, IntoFont, RGBColor, BLACK, WHITE,
+    },
+};
+
+use house_dashboard_common::{
+    colormap::Colormap, configuration::StyleConfiguration, palette::SystemColor,
+};
+
+use crate::data::{Direction, Stat};
+use crate::Error;
+use crate::SingleStatConfiguration;
+
+/// Size of values relative to the font size of labels
+const VALUE_SCALE: f64 = 4.0;
+
+/// Size of units relative to the font size of labels
+const UNIT_SCALE: f64 = 2.0;
+
+/// Space between value, unit and direction arrow in pixels
+const SPACING: i32 = 4;
+
+/// Draw a single-stat chart
+///
+/// # Errors
+///
+/// Return and error when chart generation failed
+pub fn draw_single_stat(
+    single_stat: &SingleStatConfiguration,
+    stats: &[Stat],
+    style: &StyleConfiguration,
+    backend: BitMapBackend,
+) -> Result<(), Error> {
+    info!("Drawing single-stat '{}'", single_stat.title.to_lowercase());
+
+    let root = backend.into_drawing_area();
+    root.fill(&style.system_palette.pick(SystemColor::Background))?;
+
+    // Draw the title manually and create a new margin area
+    let title_height = draw_title(single_stat.title.as_str(), style, &root)?;
+    let new_root = root.margin(title_height, 5, 5, 5);
+
+    let stats = sort_stats(single_stat.tag_values.as_ref(), stats);
+    if stats.is_empty() {
+        warn!("No values to draw");
+        return Ok(());
+    }
+
+    let colormap = single_stat
+        .bounds
+        .map(|(min, max)| {
+            Colormap::new_with_bounds_and_direction(
+                single_stat.colormap.as_ref(),
+                min,
+                max,
+                single_stat.reversed,
+            )
+        })
+        .transpose()?;
+
+    let rows = new_root.split_evenly((stats.len(), 1));
+    for (row, stat) in rows.iter().zip(stats) {
+        draw_stat(single_stat, style, colormap.as_ref(), row, stat)?;
+    }
+
+    Ok(())
+}
+
+/// Sort values in the order of tag values
+///
+/// Without tag values, values are sorted by name.
+/// Values whose name is not among the tag values are skipped.
+fn sort_stats<'a>(tag_values: Option<&Vec<String>>, stats: &'a [Stat]) -> Vec<&'a Stat> {
+    if let Some(tag_values) = tag_values {
+        tag_values
+            .iter()
+            .filter_map(|name| {
+                let stat = stats.iter().find(|stat| &stat.name == name);
+                if stat.is_none() {
+                    debug!("Missing value '{}'", name);
+                }
+                stat
+            })
+            .collect()
+    } else {
+        let mut stats: Vec<&Stat> = stats.iter().collect();
+        stats.sort_by(|first, second| first.name.cmp(&second.name));
+        stats
+    }
+}
+
+/// Draw title
+fn draw_title<DB: DrawingBackend>(
+    title: &str,
+    style: &StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+) -> Result<i32, Error> {
+    let title_font = (style.font_name.as_str(), 16.0 * style.font_scale).into_font();
+    let pos = Pos::new(HPos::Center, VPos::Top);
+
+    let (width, _height) = root.dim_in_pixel();
+
+    let (_box_width, box_height) = title_font.box_size(title).map_err(|_| Error::Font)?;
+    let box_height = i32::try_from(box_height)?;
+    let box_x = i32::try_from(width)? / 2;
+    let box_y = box_height / 2;
+
+    let vertical_skip = 5;
+
+    root.draw(&Text::new(
+        title,
+        (box_x, box_y + vertical_skip),
+        title_font
+            .color(&style.system_palette.pick(SystemColor::Foreground))
+            .pos(pos),
+    ))?;
+
+    Ok(box_height * 2)
+}
+
+/// Draw a value with its name, unit, direction and recent history
+///
+/// The background is colored according to the value when the chart has a
+/// colormap, and text is drawn in black or white to stand out against it.
+/// Values are shrunk to fit in the available space.
+fn draw_stat<DB: DrawingBackend>(
+    single_stat: &SingleStatConfiguration,
+    style: &StyleConfiguration,
+    colormap: Option<&Colormap>,
+    row: &DrawingArea<DB, Shift>,
+    stat: &Stat,
+) -> Result<(), Error> {
+    debug!("Drawing value '{}'", stat.name);
+
+    let (width, height) = row.dim_in_pixel();
+    let (width, height) = (i32::try_from(width)?, i32::try_from(height)?);
+
+    let last = stat.last();
+    if last.is_none() {
+        warn!("Empty time-series '{}', cannot draw last value", stat.name);
+    }
+
+    let background = colormap
+        .zip(last)
+        .map(|(colormap, last)| colormap.get_color(last));
+    let foreground = background.map_or_else(
+        || style.system_palette.pick(SystemColor::Foreground),
+        contrasting_color,
+    );
+    if let Some(background) = background {
+        row.draw(&Rectangle::new(
+            [(0, 1), (width - 1, height - 2)],
+            background.filled(),
+        ))?;
+    }
+
+    let label_size = 8.0 * style.font_scale;
+    let label_font = (style.font_name.as_str(), label_size).into_font();
+    let (_, label_height) = label_font.box_size(&stat.name).map_err(|_| Error::Font)?;
+    let label_height = i32::try_from(label_height)?;
+    row.draw(&Text::new(
+        stat.name.as_str(),
+        (SPACING, SPACING),
+        label_font
+            .color(&foreground)
+            .pos(Pos::new(HPos::Left, VPos::Top)),
+    ))?;
+
+    let top = label_height + SPACING;
+    let sparkline_height = if single_stat.sparkline.unwrap_or(false) {
+        (height - top) / 3
+    } else {
+        0
+    };
+    let value_area = row.margin(top, sparkline_height, 0, 0);
+    let direction = stat.direction(single_stat.tolerance.unwrap_or(0.0));
+    draw_value(single_stat, style, &value_area, last, direction, foreground)?;
+
+    if sparkline_height > 0 && stat.history.len() > 1 {
+        let sparkline_area = row.margin(height - sparkline_height, SPACING, SPACING, SPACING);
+        draw_sparkline(&sparkline_area, &stat.history, foreground)?;
+    }
+
+    Ok(())
+}
+
+/// Draw a value followed by its unit and direction arrow
+///
+/// The value is centred in the area and shrunk to fit its height.
+fn draw_value<DB: DrawingBackend>(
+    single_stat: &SingleStatConfiguration,
+    style: &StyleConfiguration,
+    area: &DrawingArea<DB, Shift>,
+    last: Option<f64>,
+    direction: Option<Direction>,
+    foreground: RGBColor,
+) -> Result<(), Error> {
+    let (width, height) = area.dim_in_pixel();
+    let (width, height) = (i32::try_from(width)?, i32::try_from(height)?);
+
+    let value_text = last.map_or_else(
+        || "-".to_owned(),
+        |last| {
+            let precision = single_stat.precision.unwrap_or(0);
+            format!("{last:.precision$}")
+        },
+    );
+    let unit_text = single_stat.unit.as_deref().unwrap_or_default();
+
+    let label_size = 8.0 * style.font_scale;
+    let available_height = f64::from(height - 2 * SPACING).max(1.0);
+    let value_size = (label_size * VALUE_SCALE).min(available_height);
+    let value_font = (style.font_name.as_str(), value_size).into_font();
+    let unit_font = (
+        style.font_name.as_str(),
+        (label_size * UNIT_SCALE).min(value_size),
+    )
+        .into_font();
+    let (value_width, value_box_height) =
+        value_font.box_size(&value_text).map_err(|_| Error::Font)?;
+    let (unit_width, _) = unit_font.box_size(unit_text).map_err(|_| Error::Font)?;
+    let (value_width, value_box_height, unit_width) = (
+        i32::try_from(value_width)?,
+        i32::try_from(value_box_height)?,
+        i32::try_from(unit_width)?,
+    );
+    let arrow_size = value_box_height / 2;
+
+    let total_width = value_width
+        + if unit_text.is_empty() {
+            0
+        } else {
+            SPACING + unit_width
+        }
+        + if direction.is_some() {
+            SPACING + arrow_size
+        } else {
+            0
+        };
+    let left = (width - total_width) / 2;
+    let baseline = value_box_height + (height - value_box_height) / 2;
+
+    area.draw(&Text::new(
+        value_text,
+        (left, baseline),
+        value_font
+            .color(&foreground)
+            .pos(Pos::new(HPos::Left, VPos::Bottom)),
+    ))?;
+
+    let mut x = left + value_width + SPACING;
+    if !unit_text.is_empty() {
+        area.draw(&Text::new(
+            unit_text,
+            (x, baseline),
+            unit_font
+                .color(&foreground)
+                .pos(Pos::new(HPos::Left, VPos::Bottom)),
+        ))?;
+        x += unit_width + SPACING;
+    }
+
+    if let Some(direction) = direction {
+        let center = (x + arrow_size / 2, baseline - value_box_height / 2);
+        area.draw(&Polygon::new(
+            arrow(direction, center, arrow_size),
+            foreground.filled(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Compute the corners of a triangular arrow pointing in a direction
+///
+/// Steady values have an arrow pointing right.
+fn arrow(direction: Direction, (x, y): (i32, i32), size: i32) -> Vec<(i32, i32)> {
+    let half = size / 2;
+    match direction {
+        Direction::Rising => vec![(x - half, y + half), (x + half, y + half), (x, y - half)],
+        Direction::Falling => vec![(x - half, y - half), (x + half, y - half), (x, y + half)],
+        Direction::Steady => vec![(x - half, y - half), (x - half, y + half), (x + half, y)],
+    }
+}
+
+/// Draw the recent history of a value as a line without axes
+fn draw_sparkline<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    history: &[(DateTime<Utc>, f64)],
+    color: RGBColor,
+) -> Result<(), Error> {
+    let (Some(&(start, _)), Some(&(end, _))) = (history.first(), history.last()) else {
+        return Ok(());
+    };
+
+    let (mut min_y, mut max_y) = history
+        .iter()
+        .map(|&(_, value)| value)
+        .filter(|value| !value.is_nan())
+        .fold((f64::MAX, f64::MIN), |(min_y, max_y), value| {
+            (min_y.min(value), max_y.max(value))
+        });
+    if min_y > max_y {
+        return Ok(());
+    }
+    if (max_y - min_y).abs() < f64::EPSILON {
+        min_y -= 1.0;
+        max_y += 1.0;
+    }
+
+    let mut chart = ChartBuilder::on(area).build_cartesian_2d(start..end, min_y..max_y)?;
+    chart.draw_series(LineSeries::new(
+        history.iter().copied(),
+        color.stroke_width(2),
+    ))?;
+
+    Ok(())
+}
+
+/// Return black or white, whichever stands out more against a color
+fn contrasting_color(background: RGBColor) -> RGBColor {
+    let RGBColor(red, green, blue) = background;
+    let luminance = 0.299 * f64::from(red) + 0.587 * f64::from(green) + 0.114 * f64::from(blue);
+    if luminance > 140.0 {
+        BLACK
+    } else {
+        WHITE
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data structures for parsing configuration
+
+use serde::Deserialize;
+
+use house_dashboard_common::colormap::ColormapType;
+use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::window::TimeWindow;
+
+use house_dashboard_influxdb::{LabelTemplate, TagKeys};
+
+/// Chart configuration for single-stat charts
+#[derive(Debug, Deserialize)]
+pub struct SingleStatConfiguration {
+    /// Chart title
+    pub title: String,
+
+    /// Precision
+    pub precision: Option<usize>,
+
+    /// Unit
+    pub unit: Option<String>,
+
+    /// InfluxDB database
+    pub database: String,
+
+    /// InfluxDB measurement
+    pub measurement: String,
+
+    /// InfluxDB field
+    pub field: String,
+
+    /// InfluxDB field scale
+    pub scale: Option<f64>,
+
+    /// InfluxDB aggregator
+    pub aggregator: Option<String>,
+
+    /// InfluxDB tag names
+    pub tag: Option<TagKeys>,
+
+    /// Template for value labels
+    pub label: Option<LabelTemplate>,
+
+    /// InfluxDB tag values
+    pub tag_values: Option<Vec<String>>,
+
+    /// Time of data from now
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
+
+    /// Data frequency
+    pub how_often: Option<Iso8601Duration>,
+
+    /// Time shift of the value compared with the last value
+    pub compare_with: Option<Iso8601Duration>,
+
+    /// Largest difference from the compared value considered steady
+    pub tolerance: Option<f64>,
+
+    /// Colormap bounds
+    pub bounds: Option<(f64, f64)>,
+
+    /// Colormap
+    pub colormap: Option<ColormapType>,
+
+    /// Setting to reverse colormap
+    pub reversed: Option<bool>,
+
+    /// Setting to draw the recent history below each value
+    pub sparkline: Option<bool>,
+}
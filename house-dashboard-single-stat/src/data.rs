@@ -0,0 +1,112 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for single-stat chart data
+
+use chrono::{DateTime, Utc};
+
+/// A value shown in a single-stat chart
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stat {
+    /// Name
+    pub name: String,
+
+    /// Recent values, the last of which is shown
+    pub history: Vec<(DateTime<Utc>, f64)>,
+
+    /// Earlier value for computing the direction, if any
+    pub reference: Option<f64>,
+}
+
+impl Stat {
+    /// Return the last value, if any
+    #[must_use]
+    pub fn last(&self) -> Option<f64> {
+        self.history.last().map(|&(_, value)| value)
+    }
+
+    /// Return the direction of the last value compared with the earlier value
+    ///
+    /// Return nothing without either value.
+    #[must_use]
+    pub fn direction(&self, tolerance: f64) -> Option<Direction> {
+        let last = self.last()?;
+        let reference = self.reference?;
+        Some(Direction::between(reference, last, tolerance))
+    }
+}
+
+/// Direction of change of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The value increased
+    Rising,
+
+    /// The value decreased
+    Falling,
+
+    /// The value changed at most by the tolerance
+    Steady,
+}
+
+impl Direction {
+    /// Compute the direction of change from a value to another
+    #[must_use]
+    pub fn between(from: f64, to: f64, tolerance: f64) -> Self {
+        let difference = to - from;
+        if difference.abs() <= tolerance.abs() {
+            Self::Steady
+        } else if difference > 0.0 {
+            Self::Rising
+        } else {
+            Self::Falling
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn direction_with_tolerance() {
+        assert_eq!(Direction::between(10.0, 12.0, 0.0), Direction::Rising);
+        assert_eq!(Direction::between(10.0, 8.0, 0.0), Direction::Falling);
+        assert_eq!(Direction::between(10.0, 10.0, 0.0), Direction::Steady);
+        assert_eq!(Direction::between(10.0, 10.4, 0.5), Direction::Steady);
+        assert_eq!(Direction::between(10.0, 9.6, 0.5), Direction::Steady);
+        assert_eq!(Direction::between(10.0, 10.6, 0.5), Direction::Rising);
+    }
+
+    #[test]
+    fn direction_without_values() {
+        let instant = Utc.with_ymd_and_hms(2020, 9, 29, 12, 0, 0).single();
+        let history: Vec<(DateTime<Utc>, f64)> =
+            instant.into_iter().map(|instant| (instant, 12.3)).collect();
+
+        let stat = Stat {
+            name: "Outdoor".to_owned(),
+            history,
+            reference: None,
+        };
+        assert_eq!(stat.last(), Some(12.3));
+        assert_eq!(stat.direction(0.0), None);
+
+        let stat = Stat {
+            reference: Some(14.0),
+            ..stat
+        };
+        assert_eq!(stat.direction(0.0), Some(Direction::Falling));
+
+        let stat = Stat {
+            history: Vec::new(),
+            ..stat
+        };
+        assert_eq!(stat.direction(0.0), None);
+    }
+}
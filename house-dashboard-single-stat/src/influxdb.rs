@@ -0,0 +1,148 @@
+// Copyright Claudio Mattera 2023.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for fetching data for single-stat charts
+
+use std::collections::HashMap;
+
+use tracing::debug;
+
+use miette::{IntoDiagnostic, Report, WrapErr};
+
+use chrono::{DateTime, Utc};
+
+use house_dashboard_common::duration::duration_to_query;
+use house_dashboard_common::window::query_bounds;
+
+use house_dashboard_influxdb::Error as InfluxDBError;
+use house_dashboard_influxdb::{InfluxDBClient, LabelTemplate};
+
+use crate::configuration::SingleStatConfiguration;
+use crate::data::Stat;
+
+/// Fetch data for single-stat
+///
+/// The recent history of each value is aggregated over `how_often`.
+/// When values are compared with an earlier time, the last value before that
+/// time is fetched from the same period shifted back.
+///
+/// # Errors
+///
+/// Return and error when data could not be fetched
+pub async fn fetch_data(
+    influxdb_client: &InfluxDBClient,
+    single_stat_configuration: &SingleStatConfiguration,
+) -> Result<Vec<Stat>, Report> {
+    let (since, until) = query_bounds(
+        single_stat_configuration.how_long_ago.as_ref(),
+        single_stat_configuration.time_window,
+    )?;
+
+    let field = &single_stat_configuration.field;
+    let scale = single_stat_configuration.scale.unwrap_or(1.0);
+    let database = &single_stat_configuration.database;
+    let measurement = &single_stat_configuration.measurement;
+    let tags = single_stat_configuration
+        .tag
+        .as_ref()
+        .map(|tag| format!(",{}", tag.to_group_by()))
+        .unwrap_or_default();
+    let label = label(single_stat_configuration);
+
+    let query = format!(
+        "SELECT {scale} * {aggregator}({field}) AS value
+        FROM {database}.autogen.{measurement}
+        WHERE time < {until} AND time > {since}
+        GROUP BY time({period}){tags} FILL(none)",
+        aggregator = single_stat_configuration
+            .aggregator
+            .as_deref()
+            .unwrap_or("mean"),
+        period = single_stat_configuration
+            .how_often
+            .as_ref()
+            .map_or_else(|| Ok("1h".to_owned()), |d| duration_to_query(&d.duration))
+            .into_diagnostic()?,
+    );
+    let histories = fetch_columns(influxdb_client, &query, &label)
+        .await
+        .wrap_err("cannot fetch recent values")?;
+
+    let mut references = HashMap::new();
+    if let Some(ref compare_with) = single_stat_configuration.compare_with {
+        let shift = duration_to_query(&compare_with.duration).into_diagnostic()?;
+        let group_by = single_stat_configuration
+            .tag
+            .as_ref()
+            .map(|tag| format!(" GROUP BY {}", tag.to_group_by()))
+            .unwrap_or_default();
+        let query = format!(
+            "SELECT {scale} * last({field}) AS value
+            FROM {database}.autogen.{measurement}
+            WHERE time < {until} - {shift} AND time > {since} - {shift}{group_by}",
+        );
+        references = fetch_columns(influxdb_client, &query, &label)
+            .await
+            .wrap_err("cannot fetch earlier values")?;
+    }
+
+    let stats = histories
+        .into_iter()
+        .map(|(name, history)| {
+            let reference = references
+                .get(&name)
+                .and_then(|values| values.last())
+                .map(|&(_, value)| value);
+            Stat {
+                name,
+                history,
+                reference,
+            }
+        })
+        .collect();
+
+    Ok(stats)
+}
+
+/// Return the label template for values
+///
+/// Values without a label are named after their tag values, or after their
+/// field if they are not grouped by tags.
+fn label(single_stat_configuration: &SingleStatConfiguration) -> LabelTemplate {
+    match (
+        single_stat_configuration.label.as_ref(),
+        single_stat_configuration.tag.as_ref(),
+    ) {
+        (Some(label), _) => label.clone(),
+        (None, Some(tag)) => tag.default_label(),
+        (None, None) => LabelTemplate::new(single_stat_configuration.field.as_str()),
+    }
+}
+
+/// Fetch the value column of each combination of tag values
+async fn fetch_columns(
+    influxdb_client: &InfluxDBClient,
+    query: &str,
+    label: &LabelTemplate,
+) -> Result<HashMap<String, Vec<(DateTime<Utc>, f64)>>, Report> {
+    debug!("Query: {}", query);
+
+    let frames = match influxdb_client.fetch_frames(query).await {
+        Ok(frames) => Ok(frames),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => Ok(Vec::new()),
+        other => other,
+    }
+    .wrap_err("cannot fetch time-series")?;
+
+    frames
+        .iter()
+        .map(|frame| {
+            let name = label.render(frame.tags())?;
+            let values = frame.float_column("value").unwrap_or_default();
+            Ok((name, values))
+        })
+        .collect()
+}
@@ -6,9 +6,11 @@
 
 //! Functions for generating chart
 
+use std::collections::BTreeMap;
+
 use tracing::{debug, info};
 
-use chrono::{DateTime, Duration, Local, Timelike, Utc};
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, Timelike, Utc};
 
 use plotters::{
     backend::{BitMapBackend, DrawingBackend},
@@ -19,7 +21,7 @@
         Shift,
     },
     drawing::{DrawingArea, IntoDrawingArea},
-    element::{Rectangle, Text},
+    element::{PathElement, Rectangle, Text},
     style::{
         text_anchor::{HPos, Pos, VPos},
         Color, IntoFont,
@@ -30,6 +32,7 @@
     colormap::Colormap, configuration::StyleConfiguration, element::Colorbar, palette::SystemColor,
 };
 
+use crate::configuration::Period;
 use crate::Error;
 use crate::TemporalHeatMapConfiguration;
 
@@ -62,7 +65,7 @@ pub fn draw_temporal_heatmap(
 
     let time_series = convert_time_series_to_local_time(time_series);
 
-    let mut chart = create_chart_context(temporal_heatmap.period.max_y(), &new_root, &time_series)?;
+    let mut chart = create_chart_context(&temporal_heatmap.period, &new_root, &time_series)?;
 
     draw_axes(temporal_heatmap, style, &mut chart)?;
 
@@ -81,6 +84,18 @@ pub fn draw_temporal_heatmap(
 
     chart.draw_series(fragments)?;
 
+    match temporal_heatmap.period {
+        Period::Calendar => {
+            draw_weekday_labels(style, &chart, &root)?;
+            draw_month_labels(style, &chart, &root)?;
+            draw_month_separators(style, &mut chart)?;
+        }
+        Period::DayOfWeekOverHour => {
+            draw_weekday_labels(style, &chart, &root)?;
+        }
+        _ => {}
+    }
+
     draw_colorbar(temporal_heatmap, style, (min_y, max_y), colormap, &root)?;
 
     Ok(())
@@ -117,13 +132,16 @@ fn draw_title<DB: DrawingBackend>(
 
 /// Create a chart context
 fn create_chart_context<'a, DB: DrawingBackend + 'a>(
-    max_y: f64,
+    period: &Period,
     root: &'a DrawingArea<DB, Shift>,
     time_series: &[(DateTime<Local>, f64)],
 ) -> Result<ChartContextAlias<'a, DB>, Error> {
     debug!("Creating chart");
 
-    let (min_x, max_x, min_y, max_y) = compute_range(max_y, time_series);
+    let (min_x, max_x, min_y, max_y) = match *period {
+        Period::HourOverDay | Period::DayOverMonth => compute_range(period.max_y(), time_series),
+        _ => compute_cells_range(period, time_series),
+    };
 
     let chart = ChartBuilder::on(root)
         .margin(5)
@@ -165,6 +183,28 @@ fn compute_range(
     (padded_min_x, padded_max_x, min_y, max_y)
 }
 
+/// Compute plot range from the cells of the period
+///
+/// The range is aligned to whole cells, so that the first and last columns
+/// are not cut.
+fn compute_cells_range(
+    period: &Period,
+    time_series: &[(DateTime<Local>, f64)],
+) -> (DateTime<Local>, DateTime<Local>, f64, f64) {
+    let mut min_x: DateTime<Local> = DateTime::<Utc>::MAX_UTC.with_timezone(&Local);
+    let mut max_x: DateTime<Local> = DateTime::<Utc>::MIN_UTC.with_timezone(&Local);
+    for &(date, _value) in time_series {
+        if let Some(((x1, x2), _)) = period.instant_to_rectangle(&date) {
+            min_x = min_x.min(x1);
+            max_x = max_x.max(x2);
+        }
+    }
+
+    debug!("Plot X range: [{}, {}]", min_x, max_x);
+
+    (min_x, max_x, 0.0, period.max_y())
+}
+
 /// Compute value range
 fn compute_value_range(time_series: &[(DateTime<Local>, f64)]) -> (f64, f64) {
     let mut min_y: f64 = f64::MAX;
@@ -185,10 +225,21 @@ fn draw_axes<'a, DB: DrawingBackend + 'a>(
 ) -> Result<(), Error> {
     debug!("Drawing axis");
 
+    // Calendars have no axes, only their own labels for months and weekdays
+    if matches!(temporal_heatmap.period, Period::Calendar) {
+        return Ok(());
+    }
+
     let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
         .into_font()
         .color(&style.system_palette.pick(SystemColor::Foreground));
 
+    // Weekdays have their own labels
+    let y_labels = match temporal_heatmap.period {
+        Period::DayOfWeekOverHour => 0,
+        _ => 4,
+    };
+
     chart
         .configure_mesh()
         .disable_mesh()
@@ -198,7 +249,7 @@ fn draw_axes<'a, DB: DrawingBackend + 'a>(
             d.format(temporal_heatmap.period.xlabel_format())
                 .to_string()
         })
-        .y_labels(4)
+        .y_labels(y_labels)
         .y_label_formatter(&|value| {
             format!("{0:.1$}", value, temporal_heatmap.precision.unwrap_or(0),)
         })
@@ -211,27 +262,176 @@ fn draw_axes<'a, DB: DrawingBackend + 'a>(
 }
 
 /// Create chart fragments
+///
+/// Values falling in the same cell are averaged.
 fn create_fragments(
     temporal_heatmap: &TemporalHeatMapConfiguration,
     colormap: &Colormap,
     time_series: &[(DateTime<Local>, f64)],
 ) -> Vec<Rectangle<(DateTime<Local>, f64)>> {
-    time_series
-        .iter()
-        .filter_map(|&(instant, value)| {
-            temporal_heatmap
-                .period
-                .instant_to_rectangle(instant)
-                .map(|((x1, x2), (y1, y2))| {
-                    Rectangle::new(
-                        [(x1, f64::from(y1)), (x2, f64::from(y2))],
-                        colormap.get_color(value).filled(),
-                    )
-                })
+    let mut cells = BTreeMap::new();
+    for &(instant, value) in time_series {
+        if let Some(rectangle) = temporal_heatmap.period.instant_to_rectangle(&instant) {
+            let (sum, count) = cells.entry(rectangle).or_insert((0.0, 0_u32));
+            *sum += value;
+            *count += 1;
+        }
+    }
+
+    cells
+        .into_iter()
+        .map(|(((x1, x2), (y1, y2)), (sum, count))| {
+            let value = sum / f64::from(count);
+            let mut rectangle = Rectangle::new(
+                [(x1, f64::from(y1)), (x2, f64::from(y2))],
+                colormap.get_color(value).filled(),
+            );
+            if matches!(temporal_heatmap.period, Period::Calendar) {
+                // Leave a gap between days
+                rectangle.set_margin(0, 1, 0, 1);
+            }
+            rectangle
         })
         .collect()
 }
 
+/// Draw weekday labels on the left of their rows
+fn draw_weekday_labels<'a, DB: DrawingBackend + 'a>(
+    style: &StyleConfiguration,
+    chart: &ChartContextAlias<'a, DB>,
+    root: &DrawingArea<DB, Shift>,
+) -> Result<(), Error> {
+    debug!("Drawing weekday labels");
+
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground));
+
+    let area = chart.plotting_area();
+    let x_range = area.get_x_range();
+
+    for (offset, name) in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+        .into_iter()
+        .enumerate()
+    {
+        let row = f64::from(6 - u32::try_from(offset)?) + 0.5;
+        let (x, y) = area.map_coordinate(&(x_range.start, row));
+        root.draw(&Text::new(
+            name,
+            (x - 4, y),
+            label_font.pos(Pos::new(HPos::Right, VPos::Center)),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Draw month labels of a calendar
+///
+/// Months are labelled below the first column starting in them.
+/// Labels that would overlap the previous one are skipped.
+fn draw_month_labels<'a, DB: DrawingBackend + 'a>(
+    style: &StyleConfiguration,
+    chart: &ChartContextAlias<'a, DB>,
+    root: &DrawingArea<DB, Shift>,
+) -> Result<(), Error> {
+    debug!("Drawing month labels");
+
+    let font = (style.font_name.as_str(), 8.0 * style.font_scale).into_font();
+    let label_font = font
+        .clone()
+        .color(&style.system_palette.pick(SystemColor::Foreground));
+
+    let area = chart.plotting_area();
+    let x_range = area.get_x_range();
+
+    let mut previous_end = i32::MIN;
+    for month in months(x_range.start.date_naive(), x_range.end.date_naive()) {
+        // Label the first column whose Monday is in the month
+        let offset = (7 - month.weekday().num_days_from_monday()) % 7;
+        let monday = month + Duration::days(i64::from(offset));
+        let Some(instant) = monday
+            .and_hms_opt(12, 0, 0)
+            .and_then(|naive| naive.and_local_timezone(Local).earliest())
+        else {
+            continue;
+        };
+        if instant >= x_range.end {
+            continue;
+        }
+        let (x, y) = area.map_coordinate(&(instant, 0.0));
+        let label = month.format("%b").to_string();
+        let (label_width, _) = font.box_size(&label).map_err(|_| Error::Font)?;
+        if x < previous_end {
+            continue;
+        }
+        previous_end = x + i32::try_from(label_width)? + 2;
+        root.draw(&Text::new(
+            label,
+            (x, y + 4),
+            label_font.pos(Pos::new(HPos::Left, VPos::Top)),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Draw separators between months of a calendar
+///
+/// Each separator follows the edges of the days, going down the column
+/// containing the first day of the month.
+fn draw_month_separators<'a, DB: DrawingBackend + 'a>(
+    style: &StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+) -> Result<(), Error> {
+    debug!("Drawing month separators");
+
+    let color = style.system_palette.pick(SystemColor::Foreground);
+    let x_range = chart.plotting_area().get_x_range();
+
+    let separators = months(x_range.start.date_naive(), x_range.end.date_naive())
+        .filter_map(|month| {
+            let instant = month
+                .and_hms_opt(12, 0, 0)
+                .and_then(|naive| naive.and_local_timezone(Local).earliest())?;
+            let ((monday, next_monday), (_, top)) =
+                Period::Calendar.instant_to_rectangle(&instant)?;
+            if monday <= x_range.start {
+                return None;
+            }
+            let top = f64::from(top);
+            let points = if top < 7.0 {
+                vec![
+                    (monday, 0.0),
+                    (monday, top),
+                    (next_monday, top),
+                    (next_monday, 7.0),
+                ]
+            } else {
+                // Months starting on Monday are separated by a straight line
+                vec![(monday, 0.0), (monday, 7.0)]
+            };
+            Some(PathElement::new(points, color))
+        })
+        .collect::<Vec<_>>();
+
+    chart.draw_series(separators)?;
+
+    Ok(())
+}
+
+/// Iterate over the first days of months in a range of dates
+fn months(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    let first = start.with_day(1).unwrap_or(start);
+    let first = if first < start {
+        first.checked_add_months(Months::new(1))
+    } else {
+        Some(first)
+    };
+    std::iter::successors(first, |month| month.checked_add_months(Months::new(1)))
+        .take_while(move |month| *month < end)
+}
+
 /// Draw a colorbar
 fn draw_colorbar<DB>(
     temporal_heatmap: &TemporalHeatMapConfiguration,
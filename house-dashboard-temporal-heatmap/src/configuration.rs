@@ -8,9 +8,11 @@
 
 use serde::Deserialize;
 
-use chrono::{DateTime, Datelike, Duration, Local, Timelike};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Timelike};
 
 use house_dashboard_common::colormap::ColormapType;
+use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::window::TimeWindow;
 
 /// Chart configuration for temporal heatmap charts
 #[derive(Debug, Deserialize)]
@@ -48,6 +50,12 @@ pub struct TemporalHeatMapConfiguration {
     /// Period
     pub period: Period,
 
+    /// Time of data from now, or the default time of the period if missing
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
+
     /// Heatmap bounds
     pub bounds: Option<(f64, f64)>,
 
@@ -69,17 +77,48 @@ pub enum Period {
 
     /// Day over month
     DayOverMonth,
+
+    /// Weekday over week, as a calendar with one column per week
+    Calendar,
+
+    /// Weekday over hour of day, averaged over all weeks
+    DayOfWeekOverHour,
+
+    /// Week over year
+    WeekOverYear,
+
+    /// Minute over hour
+    MinuteOverHour,
 }
 
 /// A rectangle whose X values are on time domain
-type TemporalRectangle = ((DateTime<Local>, DateTime<Local>), (u32, u32));
+type TemporalRectangle<Tz> = ((DateTime<Tz>, DateTime<Tz>), (u32, u32));
 
 impl<'a> Period {
     /// Map period to InfluxDB query group
     pub fn to_query_group(&self) -> &'a str {
         match *self {
-            Period::HourOverDay => "1h",
-            Period::DayOverMonth => "1d",
+            Period::HourOverDay | Period::DayOfWeekOverHour => "1h",
+            Period::DayOverMonth | Period::Calendar => "1d",
+            // InfluxDB weeks start on Thursday, since the epoch does, so
+            // they are offset to start on Monday in the local timezone
+            Period::WeekOverYear => "1w,4d",
+            Period::MinuteOverHour => "1m",
+        }
+    }
+
+    /// Map period to InfluxDB fill option
+    ///
+    /// Calendar days without data are left empty, instead of repeating the
+    /// previous day.
+    pub fn to_query_fill(&self) -> &'a str {
+        match *self {
+            Period::Calendar => "none",
+            Period::HourOverDay
+            | Period::DayOverMonth
+            | Period::DayOfWeekOverHour
+            | Period::WeekOverYear
+            | Period::MinuteOverHour => "previous",
         }
     }
 
@@ -88,6 +127,9 @@ pub fn max_y(&self) -> f64 {
         match *self {
             Period::HourOverDay => f64::from(24 + 1),
             Period::DayOverMonth => f64::from(31 + 1),
+            Period::Calendar | Period::DayOfWeekOverHour => f64::from(7),
+            Period::WeekOverYear => f64::from(53 + 1),
+            Period::MinuteOverHour => f64::from(60 + 1),
         }
     }
 
@@ -95,7 +137,9 @@ pub fn max_y(&self) -> f64 {
     pub fn xlabel(&self) -> &'a str {
         match *self {
             Period::HourOverDay => "Day",
-            Period::DayOverMonth => "Month",
+            Period::DayOverMonth | Period::Calendar => "Month",
+            Period::DayOfWeekOverHour | Period::MinuteOverHour => "Hour",
+            Period::WeekOverYear => "Year",
         }
     }
 
@@ -103,7 +147,9 @@ pub fn xlabel(&self) -> &'a str {
     pub fn xlabel_format(&self) -> &'a str {
         match *self {
             Period::HourOverDay => "%d %b",
-            Period::DayOverMonth => "%b",
+            Period::DayOverMonth | Period::Calendar => "%b",
+            Period::DayOfWeekOverHour | Period::MinuteOverHour => "%H:%M",
+            Period::WeekOverYear => "%b %Y",
         }
     }
 
@@ -111,51 +157,273 @@ pub fn xlabel_format(&self) -> &'a str {
     pub fn ylabel(&self) -> &'a str {
         match *self {
             Period::HourOverDay => "Hour",
-            Period::DayOverMonth => "Day",
+            Period::DayOverMonth | Period::Calendar | Period::DayOfWeekOverHour => "Day",
+            Period::WeekOverYear => "Week",
+            Period::MinuteOverHour => "Minute",
         }
     }
 
-    /// Get period time from present
+    /// Get period default time from present
     pub fn how_long_ago(&self) -> &'a str {
         match *self {
-            Period::HourOverDay => "30d",
-            Period::DayOverMonth => "365d",
+            Period::HourOverDay | Period::DayOfWeekOverHour => "30d",
+            Period::DayOverMonth | Period::Calendar => "365d",
+            Period::WeekOverYear => "730d",
+            Period::MinuteOverHour => "1d",
         }
     }
 
     /// Map period to a rectangle
-    pub fn instant_to_rectangle(&self, instant: DateTime<Local>) -> Option<TemporalRectangle> {
+    ///
+    /// Days, weeks, months and years start at midnight in the time zone of
+    /// the instant, so their length changes on daylight saving time
+    /// transitions.
+    pub fn instant_to_rectangle<Tz: TimeZone>(
+        &self,
+        instant: &DateTime<Tz>,
+    ) -> Option<TemporalRectangle<Tz>> {
+        let timezone = instant.timezone();
         match *self {
             Period::HourOverDay => {
+                let date = instant.date_naive();
                 let hour = instant.hour();
-                let next_hour = hour + 1;
-                if let Some(date) = instant.with_hour(0) {
-                    let next_date = date + Duration::days(1);
-                    Some(((date, next_date), (hour, next_hour)))
-                } else {
-                    None
-                }
+                Some((
+                    (
+                        start_of_day(&timezone, date)?,
+                        start_of_day(&timezone, date.succ_opt()?)?,
+                    ),
+                    (hour, hour + 1),
+                ))
             }
             Period::DayOverMonth => {
+                let month = instant.date_naive().with_day(1)?;
+                let next_month = month.checked_add_months(Months::new(1))?;
                 let day = instant.day();
-                let next_day = day + 1;
-                if let Some(date) = instant.with_day(1) {
-                    let next_date = match date.month() {
-                        1 | 3 | 5 | 7 | 8 | 19 => date + Duration::days(31),
-                        4 | 6 | 9 | 11 => date + Duration::days(30),
-                        _ => {
-                            if (date + Duration::days(28)).day() == 29 {
-                                date + Duration::days(29)
-                            } else {
-                                date + Duration::days(28)
-                            }
-                        }
-                    };
-                    Some(((date, next_date), (day, next_day)))
-                } else {
-                    None
-                }
+                Some((
+                    (
+                        start_of_day(&timezone, month)?,
+                        start_of_day(&timezone, next_month)?,
+                    ),
+                    (day, day + 1),
+                ))
+            }
+            Period::Calendar => {
+                let date = instant.date_naive();
+                let weekday = date.weekday().num_days_from_monday();
+                let monday = date - Duration::days(i64::from(weekday));
+                let next_monday = monday + Duration::weeks(1);
+                // Monday is on the top row
+                let row = 6 - weekday;
+                Some((
+                    (
+                        start_of_day(&timezone, monday)?,
+                        start_of_day(&timezone, next_monday)?,
+                    ),
+                    (row, row + 1),
+                ))
+            }
+            Period::DayOfWeekOverHour => {
+                // All weeks are drawn over the same reference day
+                let hour = reference_day(&timezone)? + Duration::hours(i64::from(instant.hour()));
+                let row = 6 - instant.weekday().num_days_from_monday();
+                Some(((hour.clone(), hour + Duration::hours(1)), (row, row + 1)))
+            }
+            Period::WeekOverYear => {
+                let week = instant.iso_week();
+                let year = NaiveDate::from_ymd_opt(week.year(), 1, 1)?;
+                let next_year = NaiveDate::from_ymd_opt(week.year() + 1, 1, 1)?;
+                Some((
+                    (
+                        start_of_day(&timezone, year)?,
+                        start_of_day(&timezone, next_year)?,
+                    ),
+                    (week.week(), week.week() + 1),
+                ))
+            }
+            Period::MinuteOverHour => {
+                let minute = instant.minute();
+                let hour = instant.clone()
+                    - Duration::minutes(i64::from(minute))
+                    - Duration::seconds(i64::from(instant.second()))
+                    - Duration::nanoseconds(i64::from(instant.nanosecond()));
+                Some((
+                    (hour.clone(), hour + Duration::hours(1)),
+                    (minute, minute + 1),
+                ))
             }
         }
     }
 }
+
+/// Return the day on which weekly patterns are drawn
+///
+/// Any day without daylight saving time transitions would do.
+fn reference_day<Tz: TimeZone>(timezone: &Tz) -> Option<DateTime<Tz>> {
+    start_of_day(timezone, NaiveDate::from_ymd_opt(2024, 1, 1)?)
+}
+
+/// Return the first instant of a date in a time zone
+///
+/// This is midnight, unless midnight is skipped by a daylight saving time
+/// transition.
+fn start_of_day<Tz: TimeZone>(timezone: &Tz, date: NaiveDate) -> Option<DateTime<Tz>> {
+    (0..24).find_map(|hour| {
+        date.and_hms_opt(hour, 0, 0)
+            .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use chrono_tz::{America::Sao_Paulo, Asia::Kolkata, Europe::Copenhagen, Tz};
+
+    /// Map an instant to its cell, as formatted local start, length and rows
+    fn cell(
+        period: &Period,
+        instant: Option<DateTime<Tz>>,
+    ) -> Option<(String, Duration, (u32, u32))> {
+        instant
+            .and_then(|instant| period.instant_to_rectangle(&instant))
+            .map(|((start, end), rows)| {
+                let start_text = start.format("%Y-%m-%d %H:%M").to_string();
+                (start_text, end - start, rows)
+            })
+    }
+
+    /// Create an instant in Copenhagen
+    fn copenhagen(month: u32, day: u32, hour: u32, minute: u32) -> Option<DateTime<Tz>> {
+        Copenhagen
+            .with_ymd_and_hms(2023, month, day, hour, minute, 0)
+            .single()
+    }
+
+    /// Create an instant from UTC time in Copenhagen
+    fn copenhagen_from_utc(month: u32, day: u32, hour: u32, minute: u32) -> Option<DateTime<Tz>> {
+        Utc.with_ymd_and_hms(2023, month, day, hour, minute, 0)
+            .single()
+            .map(|instant| instant.with_timezone(&Copenhagen))
+    }
+
+    #[test]
+    fn hour_over_day_regular_day() {
+        let expected = Some(("2023-06-15 00:00".to_owned(), Duration::hours(24), (10, 11)));
+        let actual = cell(&Period::HourOverDay, copenhagen(6, 15, 10, 45));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn hour_over_day_23_hours_day() {
+        let expected = Some(("2023-03-26 00:00".to_owned(), Duration::hours(23), (3, 4)));
+        // Clocks skip from 02:00 to 03:00
+        let actual = cell(&Period::HourOverDay, copenhagen_from_utc(3, 26, 1, 30));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn hour_over_day_25_hours_day() {
+        // Clocks go back from 03:00 to 02:00, so 02:30 happens twice
+        let first = cell(&Period::HourOverDay, copenhagen_from_utc(10, 29, 0, 30));
+        let second = cell(&Period::HourOverDay, copenhagen_from_utc(10, 29, 1, 30));
+        let expected = Some(("2023-10-29 00:00".to_owned(), Duration::hours(25), (2, 3)));
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+    }
+
+    #[test]
+    fn hour_over_day_skipped_midnight() {
+        // Clocks used to skip from 00:00 to 01:00 in São Paulo
+        let instant = Sao_Paulo.with_ymd_and_hms(2018, 11, 4, 12, 0, 0).single();
+        let expected = Some(("2018-11-04 01:00".to_owned(), Duration::hours(23), (12, 13)));
+        let actual = cell(&Period::HourOverDay, instant);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_over_month_december() {
+        let expected = Some(("2023-12-01 00:00".to_owned(), Duration::days(31), (31, 32)));
+        let actual = cell(&Period::DayOverMonth, copenhagen(12, 31, 18, 0));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_over_month_february() {
+        let expected = Some(("2023-02-01 00:00".to_owned(), Duration::days(28), (28, 29)));
+        let actual = cell(&Period::DayOverMonth, copenhagen(2, 28, 12, 0));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_over_month_february_leap_year() {
+        let instant = Copenhagen.with_ymd_and_hms(2024, 2, 29, 12, 0, 0).single();
+        let expected = Some(("2024-02-01 00:00".to_owned(), Duration::days(29), (29, 30)));
+        let actual = cell(&Period::DayOverMonth, instant);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_over_month_daylight_saving_time() {
+        let expected = Some((
+            "2023-10-01 00:00".to_owned(),
+            Duration::days(31) + Duration::hours(1),
+            (29, 30),
+        ));
+        let actual = cell(&Period::DayOverMonth, copenhagen(10, 29, 12, 0));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn calendar_daylight_saving_time() {
+        let expected = Some((
+            "2023-03-20 00:00".to_owned(),
+            Duration::weeks(1) - Duration::hours(1),
+            (0, 1),
+        ));
+        let actual = cell(&Period::Calendar, copenhagen(3, 26, 12, 0));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn calendar_skipped_midnight() {
+        // Clocks used to skip from 00:00 to 01:00 in São Paulo on a Sunday
+        let instant = Sao_Paulo.with_ymd_and_hms(2018, 11, 4, 12, 0, 0).single();
+        let expected = Some((
+            "2018-10-29 00:00".to_owned(),
+            Duration::weeks(1) - Duration::hours(1),
+            (0, 1),
+        ));
+        let actual = cell(&Period::Calendar, instant);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_of_week_over_hour_local_weekday() {
+        // Monday in Copenhagen, but still Sunday in UTC
+        let expected = Some(("2024-01-01 00:00".to_owned(), Duration::hours(1), (6, 7)));
+        let actual = cell(
+            &Period::DayOfWeekOverHour,
+            copenhagen_from_utc(1, 1, 23, 30),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn week_over_year_local_week() {
+        // First ISO week of 2023 in Copenhagen, but still last week of 2022
+        // in UTC
+        let expected = Some(("2023-01-01 00:00".to_owned(), Duration::days(365), (1, 2)));
+        let actual = cell(&Period::WeekOverYear, copenhagen_from_utc(1, 1, 23, 30));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn minute_over_hour_half_hour_offset() {
+        // Hours in Kolkata start at half past the hour in UTC
+        let instant = Kolkata.with_ymd_and_hms(2023, 6, 15, 12, 45, 0).single();
+        let expected = Some(("2023-06-15 12:00".to_owned(), Duration::hours(1), (45, 46)));
+        let actual = cell(&Period::MinuteOverHour, instant);
+        assert_eq!(actual, expected);
+    }
+}
@@ -11,17 +11,24 @@
 use tracing::debug;
 
 use miette::miette;
-use miette::{IntoDiagnostic, Report, WrapErr};
+use miette::{Report, WrapErr};
 
 use chrono::{DateTime, Utc};
 
+use house_dashboard_common::window::{local_tz_clause, query_bounds};
+
 use house_dashboard_influxdb::Error as InfluxDBError;
-use house_dashboard_influxdb::InfluxDBClient;
+use house_dashboard_influxdb::{InfluxDBClient, LabelTemplate};
 
 use crate::configuration::TemporalHeatMapConfiguration;
 
 /// Fetch data for temporal heatmap
 ///
+/// Data is fetched from `how_long_ago` or over `time_window`, or from the
+/// default time of the period if both are missing.
+/// Intervals are aligned to the local timezone, so that days start at local
+/// midnight.
+///
 /// # Errors
 ///
 /// Return and error when data could not be fetched
@@ -29,10 +36,24 @@ pub async fn fetch_data(
     influxdb_client: &InfluxDBClient,
     temporal_heatmap_configuration: &TemporalHeatMapConfiguration,
 ) -> Result<Vec<(DateTime<Utc>, f64)>, Report> {
+    let (since, until) = match (
+        temporal_heatmap_configuration.how_long_ago.as_ref(),
+        temporal_heatmap_configuration.time_window,
+    ) {
+        (None, None) => (
+            format!(
+                "now() - {}",
+                temporal_heatmap_configuration.period.how_long_ago()
+            ),
+            "now()".to_owned(),
+        ),
+        (how_long_ago, time_window) => query_bounds(how_long_ago, time_window)?,
+    };
+
     let query = format!(
         "SELECT {scale} * {aggregator}({field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago} AND {tag} = '{tag_value}'
-        GROUP BY time({period}),{tag} FILL(previous)",
+        WHERE time < {until} AND time > {since} AND {tag} = '{tag_value}'
+        GROUP BY time({period}),{tag} FILL({fill}) {tz}",
         scale = temporal_heatmap_configuration.scale.unwrap_or(1.0),
         aggregator = temporal_heatmap_configuration
             .aggregator
@@ -44,20 +65,25 @@ pub async fn fetch_data(
         tag = temporal_heatmap_configuration.tag,
         tag_value = &temporal_heatmap_configuration.tag_value,
         period = temporal_heatmap_configuration.period.to_query_group(),
-        how_long_ago = temporal_heatmap_configuration.period.how_long_ago(),
+        fill = temporal_heatmap_configuration.period.to_query_fill(),
+        tz = local_tz_clause()?,
     );
 
     debug!("Query: {}", query);
 
     let mut time_seriess = match influxdb_client
-        .fetch_tagged_dataframes(&query, &temporal_heatmap_configuration.tag)
+        .fetch_tagged_dataframes(
+            &query,
+            &LabelTemplate::for_tag(&temporal_heatmap_configuration.tag),
+        )
         .await
     {
         Ok(time_seriess) => Ok(time_seriess),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => {
+            Ok(HashMap::new())
+        }
         other => other,
     }
-    .into_diagnostic()
     .wrap_err("cannot fetch time-series")?;
 
     let time_series = time_seriess
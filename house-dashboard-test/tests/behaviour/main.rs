@@ -19,6 +19,7 @@
 use std::collections::{HashMap, HashSet};
 use std::io::{BufWriter, Cursor};
 use std::path::Path;
+use std::str::FromStr;
 
 use plotters::backend::BitMapBackend;
 use plotters::style::{register_font, FontStyle};
@@ -41,6 +42,8 @@
 
 use house_dashboard_common::configuration::StyleConfiguration;
 
+use house_dashboard_forecast::{draw_forecast, Condition, Forecast, ForecastConfiguration};
+use house_dashboard_gauge::{draw_gauge, GaugeConfiguration};
 use house_dashboard_geographical_heatmap::{
     draw_geographical_heatmap, GeographicalHeatMapConfiguration,
 };
@@ -48,32 +51,60 @@
     draw_infrastructure_summary, InfrastructureSummaryConfiguration,
 };
 use house_dashboard_proxmox_summary::{draw_proxmox_summary, ProxmoxSummaryConfiguration};
+use house_dashboard_single_stat::{draw_single_stat, SingleStatConfiguration, Stat};
 use house_dashboard_temporal_heatmap::{draw_temporal_heatmap, TemporalHeatMapConfiguration};
-use house_dashboard_trend::{draw_trend, TrendConfiguration};
+use house_dashboard_trend::{
+    draw_trend, Annotation, Axis, SeriesOptions, SeriesStyle, TrendConfiguration, TrendData,
+};
 
 const TESTS_PATH: &str = "tests";
 const DATA_PATH: &str = "tests/data";
 
+/// Time zone in which all expected bitmaps were rendered
+const TIME_ZONE: &str = "Europe/Copenhagen";
+
 fn main() {
+    // Pin the local time zone before any thread is spawned, so that charts
+    // are rendered the same regardless of the host configuration
+    std::env::set_var("TZ", TIME_ZONE);
+
     async_std::task::block_on(async {
         DashboardWorld::run(Path::new(TESTS_PATH).join("features/")).await;
     });
 }
 
 type TimeSeries = Vec<(DateTime<Utc>, f64)>;
+type Envelope = Vec<(DateTime<Utc>, f64, f64)>;
+type RawForecasts = Vec<(
+    DateTime<Utc>,
+    Option<String>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+)>;
 
 #[derive(Debug, Default, World)]
 struct DashboardWorld {
     x_range: Option<(DateTime<Local>, DateTime<Local>)>,
     style: Option<StyleConfiguration>,
     trend: Option<TrendConfiguration>,
+    single_stat: Option<SingleStatConfiguration>,
+    gauge: Option<GaugeConfiguration>,
+    forecast: Option<ForecastConfiguration>,
     geographical_heatmap: Option<GeographicalHeatMapConfiguration>,
     temporal_heatmap: Option<TemporalHeatMapConfiguration>,
     infrastructure_summary: Option<InfrastructureSummaryConfiguration>,
     proxmox_summary: Option<ProxmoxSummaryConfiguration>,
     time_series_mapping: Option<HashMap<String, TimeSeries>>,
+    series_styles: Option<HashMap<String, SeriesStyle>>,
+    envelopes_mapping: Option<HashMap<String, Envelope>>,
+    forecast_series: Option<String>,
+    comparisons_mapping: Option<HashMap<String, TimeSeries>>,
+    annotations: Option<Vec<(DateTime<Utc>, String)>>,
+    references_mapping: Option<HashMap<String, f64>>,
     time_series: Option<TimeSeries>,
     values_mapping: Option<HashMap<String, Option<f64>>>,
+    forecasts: Option<Vec<Forecast>>,
     hosts: Option<HashSet<String>>,
     statuses: Option<HashMap<String, String>>,
     loads: Option<HashMap<String, f64>>,
@@ -94,6 +125,72 @@ async fn given_time_series_mapping(
     Ok(())
 }
 
+#[given(expr = "the series styles {string}")]
+async fn given_series_styles(
+    world: &mut DashboardWorld,
+    series_styles_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let series_styles_path = Path::new(DATA_PATH).join(series_styles_filename);
+    let series_styles_content = read_file_to_string(series_styles_path).await?;
+    let series_styles: HashMap<String, SeriesStyle> = from_json_str(&series_styles_content)?;
+    world.series_styles = Some(series_styles);
+    Ok(())
+}
+
+#[given(expr = "the envelopes mapping {string}")]
+async fn given_envelopes_mapping(
+    world: &mut DashboardWorld,
+    envelopes_mapping_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let envelopes_mapping_path = Path::new(DATA_PATH).join(envelopes_mapping_filename);
+    let envelopes_mapping_content = read_file_to_string(envelopes_mapping_path).await?;
+    let envelopes_mapping: HashMap<String, Envelope> = from_json_str(&envelopes_mapping_content)?;
+    world.envelopes_mapping = Some(envelopes_mapping);
+    Ok(())
+}
+
+#[given(expr = "the forecast series {string}")]
+fn given_forecast_series(world: &mut DashboardWorld, forecast_series: String) {
+    world.forecast_series = Some(forecast_series);
+}
+
+#[given(expr = "the comparisons mapping {string}")]
+async fn given_comparisons_mapping(
+    world: &mut DashboardWorld,
+    comparisons_mapping_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let comparisons_mapping_path = Path::new(DATA_PATH).join(comparisons_mapping_filename);
+    let comparisons_mapping_content = read_file_to_string(comparisons_mapping_path).await?;
+    let comparisons_mapping: HashMap<String, TimeSeries> =
+        from_json_str(&comparisons_mapping_content)?;
+    world.comparisons_mapping = Some(comparisons_mapping);
+    Ok(())
+}
+
+#[given(expr = "the annotations {string}")]
+async fn given_annotations(
+    world: &mut DashboardWorld,
+    annotations_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let annotations_path = Path::new(DATA_PATH).join(annotations_filename);
+    let annotations_content = read_file_to_string(annotations_path).await?;
+    let annotations: Vec<(DateTime<Utc>, String)> = from_json_str(&annotations_content)?;
+    world.annotations = Some(annotations);
+    Ok(())
+}
+
+#[given(expr = "the references mapping {string}")]
+async fn given_references_mapping(
+    world: &mut DashboardWorld,
+    references_mapping_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let references_mapping_path = Path::new(DATA_PATH).join(references_mapping_filename);
+    let references_mapping_content = read_file_to_string(references_mapping_path).await?;
+    let references_mapping: HashMap<String, f64> = from_json_str(&references_mapping_content)?;
+    world.references_mapping = Some(references_mapping);
+    Ok(())
+}
+
 #[given(expr = "the values mapping {string}")]
 async fn given_values_mapping(
     world: &mut DashboardWorld,
@@ -106,6 +203,30 @@ async fn given_values_mapping(
     Ok(())
 }
 
+#[given(expr = "the forecasts {string}")]
+async fn given_forecasts(
+    world: &mut DashboardWorld,
+    forecasts_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let forecasts_path = Path::new(DATA_PATH).join(forecasts_filename);
+    let forecasts_content = read_file_to_string(forecasts_path).await?;
+    let forecasts: RawForecasts = from_json_str(&forecasts_content)?;
+    let forecasts = forecasts
+        .into_iter()
+        .map(|(instant, condition, high, low, precipitation)| {
+            Ok(Forecast {
+                instant,
+                condition: condition.as_deref().map(Condition::from_str).transpose()?,
+                high,
+                low,
+                precipitation,
+            })
+        })
+        .collect::<Result<Vec<Forecast>, String>>()?;
+    world.forecasts = Some(forecasts);
+    Ok(())
+}
+
 #[given(expr = "the time series {string}")]
 async fn given_time_series(
     world: &mut DashboardWorld,
@@ -207,6 +328,42 @@ async fn given_trend_configuration(
     Ok(())
 }
 
+#[given(expr = "the single-stat configuration {string}")]
+async fn given_single_stat_configuration(
+    world: &mut DashboardWorld,
+    single_stat_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let single_stat_path = Path::new(DATA_PATH).join(single_stat_filename);
+    let single_stat_content = read_file_to_string(single_stat_path).await?;
+    let single_stat: SingleStatConfiguration = from_toml_str(&single_stat_content)?;
+    world.single_stat = Some(single_stat);
+    Ok(())
+}
+
+#[given(expr = "the gauge configuration {string}")]
+async fn given_gauge_configuration(
+    world: &mut DashboardWorld,
+    gauge_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let gauge_path = Path::new(DATA_PATH).join(gauge_filename);
+    let gauge_content = read_file_to_string(gauge_path).await?;
+    let gauge: GaugeConfiguration = from_toml_str(&gauge_content)?;
+    world.gauge = Some(gauge);
+    Ok(())
+}
+
+#[given(expr = "the forecast configuration {string}")]
+async fn given_forecast_configuration(
+    world: &mut DashboardWorld,
+    forecast_filename: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let forecast_path = Path::new(DATA_PATH).join(forecast_filename);
+    let forecast_content = read_file_to_string(forecast_path).await?;
+    let forecast: ForecastConfiguration = from_toml_str(&forecast_content)?;
+    world.forecast = Some(forecast);
+    Ok(())
+}
+
 #[given(expr = "the temporal heatmap configuration {string}")]
 async fn given_temporal_heatmap_configuration(
     world: &mut DashboardWorld,
@@ -267,10 +424,134 @@ async fn when_drawing_trend_chart(
     let mut buffer: Vec<u8> = vec![0; area_in_bytes];
     let backend = BitMapBackend::with_buffer(&mut buffer, style_configuration.resolution);
 
+    let mut options: HashMap<(Axis, String), SeriesOptions> = world
+        .series_styles
+        .iter()
+        .flatten()
+        .map(|(name, &style)| {
+            let options = SeriesOptions {
+                style,
+                ..SeriesOptions::default()
+            };
+            ((Axis::Primary, name.clone()), options)
+        })
+        .collect();
+    if let Some(ref forecast_series) = world.forecast_series {
+        options
+            .entry((Axis::Primary, forecast_series.clone()))
+            .or_default()
+            .forecast = true;
+    }
+
     draw_trend(
         world.trend.as_ref().unwrap(),
         world.x_range.unwrap(),
-        world.time_series_mapping.as_ref().unwrap(),
+        &TrendData {
+            time_seriess: world.time_series_mapping.clone().unwrap(),
+            envelopes: world
+                .envelopes_mapping
+                .iter()
+                .flatten()
+                .map(|(name, envelope)| ((Axis::Primary, name.clone()), envelope.clone()))
+                .collect(),
+            comparisons: world
+                .comparisons_mapping
+                .iter()
+                .flatten()
+                .map(|(name, comparison)| ((Axis::Primary, name.clone()), comparison.clone()))
+                .collect(),
+            annotations: world
+                .annotations
+                .iter()
+                .flatten()
+                .map(|(instant, text)| Annotation {
+                    instant: *instant,
+                    text: text.clone(),
+                    color: None,
+                })
+                .collect(),
+            options,
+            ..TrendData::default()
+        },
+        world.style.as_ref().unwrap(),
+        backend,
+    )?;
+
+    world.raw_image = Some(buffer);
+
+    Ok(())
+}
+
+#[when(expr = "drawing a single-stat chart")]
+async fn when_drawing_single_stat_chart(
+    world: &mut DashboardWorld,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let style_configuration = world.style.as_ref().unwrap();
+    let area = style_configuration.resolution.0 * style_configuration.resolution.1;
+    let area_in_bytes = area as usize * 3;
+    let mut buffer: Vec<u8> = vec![0; area_in_bytes];
+    let backend = BitMapBackend::with_buffer(&mut buffer, style_configuration.resolution);
+
+    let references = world.references_mapping.clone().unwrap_or_default();
+    let stats: Vec<Stat> = world
+        .time_series_mapping
+        .clone()
+        .unwrap()
+        .into_iter()
+        .map(|(name, history)| Stat {
+            reference: references.get(&name).copied(),
+            name,
+            history,
+        })
+        .collect();
+
+    draw_single_stat(
+        world.single_stat.as_ref().unwrap(),
+        &stats,
+        world.style.as_ref().unwrap(),
+        backend,
+    )?;
+
+    world.raw_image = Some(buffer);
+
+    Ok(())
+}
+
+#[when(expr = "drawing a gauge chart")]
+async fn when_drawing_gauge_chart(
+    world: &mut DashboardWorld,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let style_configuration = world.style.as_ref().unwrap();
+    let area = style_configuration.resolution.0 * style_configuration.resolution.1;
+    let area_in_bytes = area as usize * 3;
+    let mut buffer: Vec<u8> = vec![0; area_in_bytes];
+    let backend = BitMapBackend::with_buffer(&mut buffer, style_configuration.resolution);
+
+    draw_gauge(
+        world.gauge.as_ref().unwrap(),
+        world.values_mapping.as_ref().unwrap(),
+        world.style.as_ref().unwrap(),
+        backend,
+    )?;
+
+    world.raw_image = Some(buffer);
+
+    Ok(())
+}
+
+#[when(expr = "drawing a forecast chart")]
+async fn when_drawing_forecast_chart(
+    world: &mut DashboardWorld,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let style_configuration = world.style.as_ref().unwrap();
+    let area = style_configuration.resolution.0 * style_configuration.resolution.1;
+    let area_in_bytes = area as usize * 3;
+    let mut buffer: Vec<u8> = vec![0; area_in_bytes];
+    let backend = BitMapBackend::with_buffer(&mut buffer, style_configuration.resolution);
+
+    draw_forecast(
+        world.forecast.as_ref().unwrap(),
+        world.forecasts.as_ref().unwrap(),
         world.style.as_ref().unwrap(),
         backend,
     )?;
@@ -0,0 +1,17 @@
+Feature: Forecast charts
+
+    Scenario: Drawing a daily weather forecast chart
+        Given the forecast configuration "forecast/daily-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the forecasts "forecast/daily.json"
+        When drawing a forecast chart
+        Then the bitmap is saved to "forecast/daily-actual.bmp"
+        Then the bitmap is the same as "forecast/daily-expected.bmp"
+
+    Scenario: Drawing a weather forecast chart with missing data
+        Given the forecast configuration "forecast/winter-configuration.toml"
+        And the style configuration "style/dark.toml"
+        And the forecasts "forecast/winter.json"
+        When drawing a forecast chart
+        Then the bitmap is saved to "forecast/winter-actual.bmp"
+        Then the bitmap is the same as "forecast/winter-expected.bmp"
@@ -0,0 +1,17 @@
+Feature: Gauge charts
+
+    Scenario: Drawing a gauge chart of humidity
+        Given the gauge configuration "gauge/humidity-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the values mapping "gauge/humidity.json"
+        When drawing a gauge chart
+        Then the bitmap is saved to "gauge/humidity-actual.bmp"
+        Then the bitmap is the same as "gauge/humidity-expected.bmp"
+
+    Scenario: Drawing a gauge chart of CO2 with thresholds
+        Given the gauge configuration "gauge/co2-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the values mapping "gauge/co2.json"
+        When drawing a gauge chart
+        Then the bitmap is saved to "gauge/co2-actual.bmp"
+        Then the bitmap is the same as "gauge/co2-expected.bmp"
@@ -0,0 +1,10 @@
+Feature: Single-stat charts
+
+    Scenario: Drawing a single-stat chart of room temperature
+        Given the single-stat configuration "single-stat/room-temperature-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the time series mapping "single-stat/room-temperature-mapping.json"
+        And the references mapping "single-stat/room-temperature-references.json"
+        When drawing a single-stat chart
+        Then the bitmap is saved to "single-stat/room-temperature-actual.bmp"
+        Then the bitmap is the same as "single-stat/room-temperature-expected.bmp"
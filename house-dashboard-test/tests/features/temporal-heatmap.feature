@@ -17,3 +17,35 @@ Feature: Temporal heatmap charts
         When drawing a temporal heatmap chart
         Then the bitmap is saved to "temporal-heatmap/outdoor-temperature-dark-actual.bmp"
         Then the bitmap is the same as "temporal-heatmap/outdoor-temperature-dark-expected.bmp"
+
+    Scenario: Drawing a calendar temporal-heatmap chart
+        Given the temporal heatmap configuration "temporal-heatmap/electricity-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the time series "temporal-heatmap/electricity.json"
+        When drawing a temporal heatmap chart
+        Then the bitmap is saved to "temporal-heatmap/electricity-actual.bmp"
+        Then the bitmap is the same as "temporal-heatmap/electricity-expected.bmp"
+
+    Scenario: Drawing a day-of-week over hour temporal-heatmap chart
+        Given the temporal heatmap configuration "temporal-heatmap/power-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the time series "temporal-heatmap/power.json"
+        When drawing a temporal heatmap chart
+        Then the bitmap is saved to "temporal-heatmap/power-actual.bmp"
+        Then the bitmap is the same as "temporal-heatmap/power-expected.bmp"
+
+    Scenario: Drawing a week over year temporal-heatmap chart
+        Given the temporal heatmap configuration "temporal-heatmap/water-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the time series "temporal-heatmap/water.json"
+        When drawing a temporal heatmap chart
+        Then the bitmap is saved to "temporal-heatmap/water-actual.bmp"
+        Then the bitmap is the same as "temporal-heatmap/water-expected.bmp"
+
+    Scenario: Drawing a minute over hour temporal-heatmap chart
+        Given the temporal heatmap configuration "temporal-heatmap/load-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the time series "temporal-heatmap/load.json"
+        When drawing a temporal heatmap chart
+        Then the bitmap is saved to "temporal-heatmap/load-actual.bmp"
+        Then the bitmap is the same as "temporal-heatmap/load-expected.bmp"
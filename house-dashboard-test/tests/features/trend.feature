@@ -17,3 +17,116 @@ Feature: Trend charts
         When drawing a trend chart
         Then the bitmap is saved to "trend/room-temperature-dark-actual.bmp"
         Then the bitmap is the same as "trend/room-temperature-dark-expected.bmp"
+
+    Scenario: Drawing a trend chart with series styles
+        Given the trend configuration "trend/energy-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/energy-mapping.json"
+        And the series styles "trend/energy-styles.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/energy-actual.bmp"
+        Then the bitmap is the same as "trend/energy-expected.bmp"
+
+    Scenario: Drawing a trend chart with envelopes
+        Given the trend configuration "trend/room-temperature-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/room-temperature-mapping.json"
+        And the envelopes mapping "trend/room-temperature-envelopes.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/room-temperature-envelopes-actual.bmp"
+        Then the bitmap is the same as "trend/room-temperature-envelopes-expected.bmp"
+
+    Scenario: Drawing a trend chart with thresholds and target ranges
+        Given the trend configuration "trend/room-temperature-references-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/room-temperature-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/room-temperature-references-actual.bmp"
+        Then the bitmap is the same as "trend/room-temperature-references-expected.bmp"
+
+    Scenario: Drawing a trend chart with a forecast
+        Given the trend configuration "trend/outdoor-forecast-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-10-01T00:00:00Z"
+        And the time series mapping "trend/outdoor-forecast-mapping.json"
+        And the forecast series "forecast"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/outdoor-forecast-actual.bmp"
+        Then the bitmap is the same as "trend/outdoor-forecast-expected.bmp"
+
+    Scenario: Drawing a trend chart with a comparison period
+        Given the trend configuration "trend/outdoor-comparison-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/outdoor-comparison-mapping.json"
+        And the comparisons mapping "trend/outdoor-comparison-comparisons.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/outdoor-comparison-actual.bmp"
+        Then the bitmap is the same as "trend/outdoor-comparison-expected.bmp"
+
+    Scenario: Drawing a trend chart with gaps
+        Given the trend configuration "trend/outdoor-gaps-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/outdoor-gaps-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/outdoor-gaps-actual.bmp"
+        Then the bitmap is the same as "trend/outdoor-gaps-expected.bmp"
+
+    Scenario: Drawing a trend chart with annotations
+        Given the trend configuration "trend/outdoor-annotations-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/outdoor-comparison-mapping.json"
+        And the annotations "trend/outdoor-annotations.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/outdoor-annotations-actual.bmp"
+        Then the bitmap is the same as "trend/outdoor-annotations-expected.bmp"
+
+    Scenario: Drawing a trend chart with a logarithmic scale
+        Given the trend configuration "trend/air-quality-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/air-quality-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/air-quality-actual.bmp"
+        Then the bitmap is the same as "trend/air-quality-expected.bmp"
+
+    Scenario: Drawing a trend chart with an automatically placed legend
+        Given the trend configuration "trend/outdoor-legend-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/outdoor-legend-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/outdoor-legend-actual.bmp"
+        Then the bitmap is the same as "trend/outdoor-legend-expected.bmp"
+
+    Scenario: Drawing a trend chart with a legend outside the plot
+        Given the trend configuration "trend/room-temperature-legend-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/room-temperature-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/room-temperature-legend-actual.bmp"
+        Then the bitmap is the same as "trend/room-temperature-legend-expected.bmp"
+
+    Scenario: Drawing a trend chart as small multiples
+        Given the trend configuration "trend/room-temperature-multiples-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/room-temperature-multiples-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/room-temperature-multiples-actual.bmp"
+        Then the bitmap is the same as "trend/room-temperature-multiples-expected.bmp"
+
+    Scenario: Drawing a trend chart as small multiples with an empty series
+        Given the trend configuration "trend/room-temperature-multiples-empty-configuration.toml"
+        And the style configuration "style/light.toml"
+        And the data range "2020-09-29T00:00:00Z" to "2020-09-30T00:00:00Z"
+        And the time series mapping "trend/room-temperature-multiples-empty-mapping.json"
+        When drawing a trend chart
+        Then the bitmap is saved to "trend/room-temperature-multiples-empty-actual.bmp"
+        Then the bitmap is the same as "trend/room-temperature-multiples-empty-expected.bmp"
@@ -7,55 +7,260 @@
 //! Functions for generating chart
 
 use std::collections::HashMap;
-use std::hash::BuildHasher;
 
 use itertools::Itertools;
 
 use tracing::{debug, info, warn};
 
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Duration, Local, Utc};
 
 use plotters::{
     backend::{BitMapBackend, DrawingBackend},
-    chart::{ChartBuilder, ChartContext, SeriesLabelPosition},
+    chart::{ChartBuilder, ChartContext, DualCoordChartContext, SeriesLabelPosition},
     coord::{
         cartesian::Cartesian2d,
         types::{RangedCoordf64, RangedDateTime},
         Shift,
     },
     drawing::{DrawingArea, IntoDrawingArea},
-    element::{Circle, PathElement, Text},
+    element::{
+        Circle, DynElement, EmptyElement, IntoDynElement, MultiLineText, PathElement, Polygon,
+        Rectangle, Text,
+    },
     series::LineSeries,
     style::{
         text_anchor::{HPos, Pos, VPos},
-        Color, IntoFont, RGBAColor,
+        Color, IntoFont, RGBAColor, RGBColor, TextStyle,
     },
 };
 
-use house_dashboard_common::{configuration::StyleConfiguration, palette::SystemColor};
+use house_dashboard_common::{
+    colormap::{Colormap, ColormapType},
+    configuration::StyleConfiguration,
+    element::DashedPath,
+    palette::SystemColor,
+};
 
+use crate::coord::ValueCoord;
 use crate::Error;
-use crate::TrendConfiguration;
+use crate::{
+    Annotation, Axis, LegendPosition, LegendStatistic, SecondaryAxisConfiguration, SeriesOptions,
+    SeriesStyle, SmallMultiplesConfiguration, TargetRangeConfiguration, TrendConfiguration,
+    TrendData,
+};
+
+/// A chart coordinate system
+type CoordAlias = Cartesian2d<RangedDateTime<DateTime<Local>>, ValueCoord>;
+
+/// A secondary chart coordinate system
+type SecondaryCoordAlias = Cartesian2d<RangedDateTime<DateTime<Local>>, RangedCoordf64>;
 
 /// A chart context
-type ChartContextAlias<'a, DB> =
-    ChartContext<'a, DB, Cartesian2d<RangedDateTime<DateTime<Local>>, RangedCoordf64>>;
+type ChartContextAlias<'a, DB> = ChartContext<'a, DB, CoordAlias>;
+
+/// A chart context with a secondary coordinate system
+type DualChartContextAlias<'a, DB> = DualCoordChartContext<'a, DB, CoordAlias, SecondaryCoordAlias>;
+
+/// Dash length of series on the secondary axis
+const SECONDARY_DASH: u32 = 6;
+
+/// Gap length of series on the secondary axis
+const SECONDARY_GAP: u32 = 4;
+
+/// Opacity of filled areas
+const AREA_OPACITY: f64 = 0.3;
+
+/// Opacity of envelopes between minimal and maximal values
+const ENVELOPE_OPACITY: f64 = 0.15;
+
+/// Opacity of target ranges
+const TARGET_RANGE_OPACITY: f64 = 0.2;
+
+/// Opacity of lines over the comparison period
+const COMPARISON_OPACITY: f64 = 0.5;
+
+/// Opacity of shaded intervals without data
+const GAP_OPACITY: f64 = 0.2;
+
+/// Dash and gap length of thresholds
+const THRESHOLD_DASH: u32 = 4;
+
+/// Dash and gap length of the current time marker
+const NOW_DASH: u32 = 2;
+
+/// Dash and gap length of event markers
+const ANNOTATION_DASH: u32 = 3;
+
+/// Maximal number of characters in event labels
+const ANNOTATION_LENGTH: usize = 20;
+
+/// Minimal horizontal space between event labels in pixels
+const ANNOTATION_SPACING: i32 = 4;
+
+/// Width of legend symbols in pixels
+const LEGEND_SYMBOL_WIDTH: u32 = 30;
+
+/// Margin around legend entries in pixels
+const LEGEND_MARGIN: u32 = 10;
+
+/// Space between the legend and the plot borders in pixels
+const LEGEND_SPACING: i32 = 5;
+
+/// A linear mapping from secondary axis values to primary axis values
+#[derive(Debug, Clone, Copy)]
+struct AxisMapping {
+    /// Secondary axis range
+    from: (f64, f64),
+
+    /// Primary axis range
+    to: (f64, f64),
+
+    /// Whether the primary axis is logarithmic
+    logarithmic: bool,
+}
+
+impl AxisMapping {
+    /// Map a secondary axis value to the primary axis
+    fn map(&self, value: f64) -> f64 {
+        let (from_min, from_max) = self.from;
+        let (to_min, to_max) = self.to;
+        if (from_max - from_min).abs() < f64::EPSILON {
+            return to_min;
+        }
+        let fraction = (value - from_min) / (from_max - from_min);
+        if self.logarithmic {
+            (to_min.ln() + fraction * (to_max.ln() - to_min.ln())).exp()
+        } else {
+            to_min + fraction * (to_max - to_min)
+        }
+    }
+}
+
+/// A point of a time-series ready to be plotted
+#[derive(Debug, Clone, Copy)]
+struct PlotPoint {
+    /// Instant
+    instant: DateTime<Local>,
+
+    /// Original value
+    value: f64,
+
+    /// Bottom of the filled region, or the value for lines
+    low: f64,
+
+    /// Top of the filled region, or the value for lines
+    high: f64,
+}
+
+/// A time-series ready to be plotted
+struct PlotSeries<'a> {
+    /// Series name
+    name: &'a str,
+
+    /// Label in the legend
+    label: String,
+
+    /// Description of statistics in the legend, if any
+    statistics: String,
+
+    /// Color index
+    index: usize,
+
+    /// Drawing options
+    options: SeriesOptions,
+
+    /// Points
+    points: Vec<PlotPoint>,
+
+    /// Minimal and maximal values, if any
+    envelope: Vec<(DateTime<Local>, f64, f64)>,
+
+    /// Values over the comparison period, if any
+    comparison: Vec<(DateTime<Local>, f64)>,
+
+    /// Position among bar series on the same axis, and their number
+    bar_slot: (usize, usize),
+
+    /// Longest interval between points connected by lines, if any
+    max_gap: Option<Duration>,
+
+    /// Target range for coloring the line with the status colormap, if any
+    status: Option<&'a TargetRangeConfiguration>,
+}
+
+impl PlotSeries<'_> {
+    /// Return the label in the legend, including statistics
+    fn legend_label(&self) -> String {
+        format!("{}{}", self.label, self.statistics)
+    }
+
+    /// Return the symbol in the legend
+    ///
+    /// Lines are dashed on the secondary axis and for forecasts.
+    fn symbol(&self, style: &StyleConfiguration, secondary: bool) -> LegendSymbol {
+        let color = style.series_palette.pick(self.index);
+        match self.options.style {
+            SeriesStyle::Line | SeriesStyle::Step if secondary || self.options.forecast => {
+                LegendSymbol::DashedLine(color.to_rgba())
+            }
+            SeriesStyle::Line | SeriesStyle::Step => LegendSymbol::Line(color.to_rgba()),
+            SeriesStyle::Area | SeriesStyle::StackedArea => {
+                LegendSymbol::Box(color.mix(AREA_OPACITY))
+            }
+            SeriesStyle::Bars => LegendSymbol::Box(color.to_rgba()),
+        }
+    }
+}
+
+/// A symbol of an entry in the legend
+#[derive(Debug, Clone, Copy)]
+enum LegendSymbol {
+    /// Solid line
+    Line(RGBAColor),
+
+    /// Dashed line
+    DashedLine(RGBAColor),
+
+    /// Filled box
+    Box(RGBAColor),
+}
+
+impl LegendSymbol {
+    /// Create the element of the symbol at a position
+    fn element<DB: DrawingBackend>(
+        self,
+        (x, y): (i32, i32),
+    ) -> DynElement<'static, DB, (i32, i32)> {
+        match self {
+            Self::Line(color) => {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)).into_dyn()
+            }
+            Self::DashedLine(color) => DashedPath::new(
+                vec![(x, y), (x + 20, y)],
+                SECONDARY_DASH,
+                SECONDARY_GAP,
+                color.stroke_width(2),
+            )
+            .into_dyn(),
+            Self::Box(color) => {
+                Rectangle::new([(x, y - 4), (x + 20, y + 4)], color.filled()).into_dyn()
+            }
+        }
+    }
+}
 
 /// Draw a trend chart
 ///
 /// # Errors
 ///
 /// Return and error when chart generation failed
-pub fn draw_trend<S>(
+pub fn draw_trend(
     trend: &TrendConfiguration,
     x_range: (DateTime<Local>, DateTime<Local>),
-    time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>, S>,
+    data: &TrendData,
     style: &StyleConfiguration,
     backend: BitMapBackend,
-) -> Result<(), Error>
-where
-    S: BuildHasher + Default,
-{
+) -> Result<(), Error> {
     info!("Drawing trend '{}'", trend.title.to_lowercase());
 
     let root = backend.into_drawing_area();
@@ -65,39 +270,337 @@ pub fn draw_trend<S>(
     let title_height = draw_title(trend.title.as_str(), style, &root)?;
     let new_root = root.margin(title_height, 0, 0, 0);
 
-    let indices = compute_indices(trend.tag_values.as_ref(), time_seriess);
+    let indices = compute_indices(
+        trend.tag_values.as_ref(),
+        &data.time_seriess,
+        &data.secondary_time_seriess,
+    );
 
-    let time_seriess = convert_time_seriess_to_local_time(time_seriess);
+    let time_seriess = convert_time_seriess_to_local_time(&data.time_seriess);
+    let secondary_time_seriess = convert_time_seriess_to_local_time(&data.secondary_time_seriess);
 
-    let mut chart = create_chart_context(
-        trend.top_padding,
-        trend.min_y_range,
-        x_range,
+    let has_secondary_axis =
+        trend.secondary_axis.is_some() || !data.secondary_time_seriess.is_empty();
+
+    let period = trend
+        .how_often
+        .as_ref()
+        .and_then(|how_often| Duration::from_std(how_often.duration.unsigned_abs()).ok())
+        .unwrap_or_else(|| Duration::hours(1));
+    let max_gap = trend.max_gap.and_then(|max_gap| {
+        let max_gap =
+            std::time::Duration::try_from_secs_f64(period.to_std().ok()?.as_secs_f64() * max_gap)
+                .ok()?;
+        Duration::from_std(max_gap).ok()
+    });
+
+    let primary_suffix = if has_secondary_axis { " (L)" } else { "" };
+    let mut seriess = prepare_seriess(
+        &time_seriess,
+        Axis::Primary,
+        data,
+        &indices,
+        (primary_suffix, max_gap),
+        status_target_range(trend, Axis::Primary),
+    );
+    let mut secondary_seriess = prepare_seriess(
+        &secondary_time_seriess,
+        Axis::Secondary,
+        data,
         &indices,
+        (" (R)", max_gap),
+        status_target_range(trend, Axis::Secondary),
+    );
+
+    if let Some(ref small_multiples) = trend.small_multiples {
+        return draw_small_multiples(
+            trend,
+            small_multiples,
+            style,
+            &new_root,
+            (x_range, period),
+            (&seriess, &secondary_seriess),
+        );
+    }
+
+    describe_statistics(trend, Axis::Primary, &mut seriess);
+    describe_statistics(trend, Axis::Secondary, &mut secondary_seriess);
+
+    let legend_entries = legend_entries(trend, style, (&seriess, &secondary_seriess));
+    let legend_size = estimate_legend_size(&legend_font(style), &legend_entries)?;
+    let chart = create_chart_context(
+        trend,
+        x_range,
+        (has_secondary_axis, legend_size.0),
         &new_root,
-        &time_seriess,
+        &seriess,
+        &trend.reference_values(Axis::Primary),
     )?;
 
-    debug!("Drawing axis");
-    draw_axes(trend, style, &mut chart)?;
+    if has_secondary_axis {
+        let secondary_axis = trend.secondary_axis.as_ref();
+        let (min_y, max_y) = secondary_range(trend, &secondary_seriess);
 
-    debug!("Plotting time-series");
-    for (name, time_series) in time_seriess.iter().sorted_by_key(|pair| pair.0) {
-        plot_time_series(
+        let primary_range = chart.y_range();
+        let mapping = AxisMapping {
+            from: (min_y, max_y),
+            to: (primary_range.start, primary_range.end),
+            logarithmic: trend.log_scale.unwrap_or(false),
+        };
+
+        let mut chart = chart.set_secondary_coord(x_range.0..x_range.1, min_y..max_y);
+
+        debug!("Drawing secondary axis");
+        draw_secondary_axis(trend, secondary_axis, style, &mut chart)?;
+
+        draw_contents(
             trend,
-            &indices,
             style,
             &new_root,
             &mut chart,
-            name,
-            time_series,
-        )?;
+            (period, legend_size),
+            (&seriess, &data.annotations),
+            Some((&secondary_seriess, mapping)),
+        )
+    } else {
+        let mut chart = chart;
+        draw_contents(
+            trend,
+            style,
+            &new_root,
+            &mut chart,
+            (period, legend_size),
+            (&seriess, &data.annotations),
+            None,
+        )
+    }
+}
+
+/// Draw axes, time-series and legend
+fn draw_contents<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+    chart: &mut ChartContextAlias<'a, DB>,
+    (period, legend_size): (Duration, (i32, i32)),
+    (seriess, annotations): (&[PlotSeries], &[Annotation]),
+    secondary: Option<(&[PlotSeries], AxisMapping)>,
+) -> Result<(), Error> {
+    debug!("Drawing axis");
+    draw_axes(trend, style, chart)?;
+
+    debug!("Drawing references");
+    let mapping = secondary.map(|(_, mapping)| mapping);
+    draw_target_ranges(trend, style, chart, mapping)?;
+    draw_thresholds(trend, style, chart, mapping)?;
+
+    if trend.how_far_ahead.is_some() {
+        draw_now_marker(trend, style, chart)?;
+    }
+
+    debug!("Drawing annotations");
+    draw_annotation_markers(style, chart, annotations)?;
+
+    if trend.shade_gaps.unwrap_or(false) {
+        debug!("Shading gaps");
+        let all_seriess = seriess.iter().chain(
+            secondary
+                .into_iter()
+                .flat_map(|(secondary_seriess, _)| secondary_seriess),
+        );
+        for series in all_seriess {
+            draw_gaps(style, chart, series)?;
+        }
+    }
+
+    debug!("Plotting envelopes");
+    for series in seriess {
+        plot_envelope(style, chart, series, None)?;
+    }
+    if let Some((secondary_seriess, mapping)) = secondary {
+        for series in secondary_seriess {
+            plot_envelope(style, chart, series, Some(mapping))?;
+        }
+    }
+
+    if trend.compare_with.is_some() {
+        debug!("Plotting comparisons");
+        for series in seriess {
+            plot_comparison(trend, style, chart, series, None)?;
+        }
+        if let Some((secondary_seriess, mapping)) = secondary {
+            for series in secondary_seriess {
+                plot_comparison(trend, style, chart, series, Some(mapping))?;
+            }
+        }
     }
 
+    debug!("Plotting time-series");
+    for series in seriess {
+        plot_series(trend, style, root, chart, period, series, None)?;
+    }
+
+    if let Some((secondary_seriess, mapping)) = secondary {
+        debug!("Plotting secondary time-series");
+        for series in secondary_seriess {
+            plot_series(trend, style, root, chart, period, series, Some(mapping))?;
+        }
+    }
+
+    draw_annotation_labels(style, chart, annotations)?;
+
     if !trend.hide_legend.unwrap_or(false) {
-        draw_legend(&mut chart, style)?;
+        draw_legend(trend, style, root, chart, legend_size, (seriess, secondary))?;
+    }
+
+    Ok(())
+}
+
+/// Draw each time-series in its own small chart
+///
+/// Charts are arranged in a grid, and only charts at the bottom of each
+/// column show the X axis.
+/// Each chart shows the series name and its last value above the plot.
+fn draw_small_multiples<DB: DrawingBackend>(
+    trend: &TrendConfiguration,
+    small_multiples: &SmallMultiplesConfiguration,
+    style: &StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+    (x_range, period): ((DateTime<Local>, DateTime<Local>), Duration),
+    (seriess, secondary_seriess): (&[PlotSeries], &[PlotSeries]),
+) -> Result<(), Error> {
+    debug!("Drawing small multiples");
+
+    let all_seriess: Vec<(&PlotSeries, Axis)> = seriess
+        .iter()
+        .map(|series| (series, Axis::Primary))
+        .chain(
+            secondary_seriess
+                .iter()
+                .map(|series| (series, Axis::Secondary)),
+        )
+        .collect();
+
+    let share_y_range = small_multiples.share_y_range.unwrap_or(false);
+    let shared_ranges = [
+        primary_range(trend, seriess, &trend.reference_values(Axis::Primary))?,
+        secondary_range(trend, secondary_seriess),
+    ];
+
+    let (rows, columns) = small_multiples.grid(all_seriess.len());
+    let cells = root.margin(0, 5, 5, 5).split_evenly((rows, columns));
+
+    for (position, (cell, (series, axis))) in cells.iter().zip(all_seriess.iter()).enumerate() {
+        let y_range = match (share_y_range, axis) {
+            (true, Axis::Primary) => shared_ranges[0],
+            (true, Axis::Secondary) => shared_ranges[1],
+            (false, Axis::Primary) => primary_range(
+                trend,
+                std::slice::from_ref(series),
+                &trend.reference_values(Axis::Primary),
+            )?,
+            (false, Axis::Secondary) => secondary_range(trend, std::slice::from_ref(series)),
+        };
+        let bottom = position + columns >= all_seriess.len();
+        draw_small_multiple(
+            trend,
+            style,
+            cell,
+            (x_range, period),
+            y_range,
+            (series, *axis),
+            bottom,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Draw a time-series in a small chart
+///
+/// Reference lines and ranges are only drawn on charts of the primary axis,
+/// since each chart has a single axis.
+fn draw_small_multiple<DB: DrawingBackend>(
+    trend: &TrendConfiguration,
+    style: &StyleConfiguration,
+    cell: &DrawingArea<DB, Shift>,
+    ((min_x, max_x), period): ((DateTime<Local>, DateTime<Local>), Duration),
+    (min_y, max_y): (f64, f64),
+    (series, axis): (&PlotSeries, Axis),
+    bottom: bool,
+) -> Result<(), Error> {
+    let foreground = style.system_palette.pick(SystemColor::Foreground);
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&foreground);
+    let value_font = (style.font_name.as_str(), 14.0 * style.font_scale).into_font();
+
+    let (precision, unit) = axis_format(trend, axis);
+    let last_value_text = series.points.last().map_or_else(
+        || {
+            warn!(
+                "Empty time-series '{}', cannot draw last value",
+                series.name
+            );
+            "-".to_owned()
+        },
+        |last_point| {
+            let unit = unit.map(|unit| format!(" {unit}")).unwrap_or_default();
+            format!("{0:.1$}{unit}", last_point.value, precision)
+        },
+    );
+
+    let (width, _height) = cell.dim_in_pixel();
+    let (_, value_height) = value_font
+        .box_size(&last_value_text)
+        .map_err(|_| Error::Font)?;
+    cell.draw(&Text::new(
+        series.name,
+        (2, 2),
+        label_font.clone().pos(Pos::new(HPos::Left, VPos::Top)),
+    ))?;
+    cell.draw(&Text::new(
+        last_value_text,
+        (i32::try_from(width)? - 2, 2),
+        value_font
+            .color(&style.series_palette.pick(series.index))
+            .pos(Pos::new(HPos::Right, VPos::Top)),
+    ))?;
+
+    let plot_area = cell.margin(value_height + 4, 0, 0, 0);
+    let logarithmic = trend.log_scale.unwrap_or(false);
+    let mut chart = ChartBuilder::on(&plot_area)
+        .margin(3)
+        .x_label_area_size(if bottom { 15 } else { 0 })
+        .y_label_area_size(30)
+        .build_cartesian_2d(min_x..max_x, ValueCoord::new(min_y..max_y, logarithmic))?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .axis_style(foreground)
+        .x_labels(trend.max_x_ticks.unwrap_or(3))
+        .x_label_formatter(&|d| d.format(&trend.xlabel_format).to_string())
+        .y_labels(trend.max_y_ticks.unwrap_or(3))
+        .y_label_formatter(&|value| format!("{value:.precision$}"))
+        .label_style(label_font)
+        .draw()?;
+
+    if axis == Axis::Primary {
+        draw_target_ranges(trend, style, &mut chart, None)?;
+        draw_thresholds(trend, style, &mut chart, None)?;
     }
 
+    if trend.shade_gaps.unwrap_or(false) {
+        draw_gaps(style, &mut chart, series)?;
+    }
+
+    plot_envelope(style, &mut chart, series, None)?;
+    if trend.compare_with.is_some() {
+        plot_comparison(trend, style, &mut chart, series, None)?;
+    }
+    plot_shape(style, &mut chart, period, series, None)?;
+
     Ok(())
 }
 
@@ -131,61 +634,188 @@ fn draw_title<DB: DrawingBackend>(
 }
 
 /// Compute plots indices
-fn compute_indices<S>(
+///
+/// Series with the same name on the primary and secondary axes share the same
+/// index, and thus the same color.
+fn compute_indices(
     tag_values: Option<&Vec<String>>,
-    time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>, S>,
-) -> HashMap<String, usize>
-where
-    S: BuildHasher,
-{
+    time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+    secondary_time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+) -> HashMap<String, usize> {
     let mut indices = HashMap::<String, usize>::new();
-    match tag_values {
-        Some(tag_values) => {
-            for (index, name) in tag_values.iter().enumerate() {
-                indices.insert(name.clone(), index);
-            }
+    if let Some(tag_values) = tag_values {
+        for (index, name) in tag_values.iter().enumerate() {
+            indices.insert(name.clone(), index);
+        }
+    } else {
+        for (index, (name, _)) in time_seriess.iter().enumerate() {
+            indices.insert(name.clone(), index);
         }
-        None => {
-            for (index, (name, _)) in time_seriess.iter().enumerate() {
-                indices.insert(name.clone(), index);
+        for name in secondary_time_seriess.keys().sorted() {
+            if !indices.contains_key(name) {
+                indices.insert(name.clone(), indices.len());
             }
         }
-    };
+    }
 
     debug!("Indices: {:?}", indices);
 
     indices
 }
 
-/// Compute plot range
-fn compute_range<S>(
-    top_padding: Option<f64>,
+/// Return the first target range on an axis with status colors
+fn status_target_range(
+    trend: &TrendConfiguration,
+    axis: Axis,
+) -> Option<&TargetRangeConfiguration> {
+    trend.target_ranges.iter().flatten().find(|target_range| {
+        target_range.status_colors.unwrap_or(false) && target_range.axis.unwrap_or_default() == axis
+    })
+}
+
+/// Prepare time-series for plotting
+///
+/// Time-series are sorted by name, and time-series without an index are
+/// skipped.
+/// Stacked areas are stacked in the same order, and their envelopes are
+/// discarded.
+/// Only lines are colored with the status colormap.
+fn prepare_seriess<'a>(
+    time_seriess: &'a HashMap<String, Vec<(DateTime<Local>, f64)>>,
+    axis: Axis,
+    data: &TrendData,
     indices: &HashMap<String, usize>,
-    time_seriess: &HashMap<String, Vec<(DateTime<Local>, f64)>, S>,
-) -> (f64, f64)
-where
-    S: BuildHasher,
-{
-    let mut min_y = std::f64::MAX;
-    let mut max_y = std::f64::MIN;
-
-    for (name, time_series) in time_seriess {
-        if !indices.contains_key(name) {
-            debug!(
-                "Skipping unexpected time-series '{}' for range computation",
-                name,
-            );
+    (label_suffix, max_gap): (&str, Option<Duration>),
+    status: Option<&'a TargetRangeConfiguration>,
+) -> Vec<PlotSeries<'a>> {
+    let bar_count = time_seriess
+        .keys()
+        .filter(|name| data.options(axis, name).style == SeriesStyle::Bars)
+        .count();
+    let mut bar_position = 0;
+
+    let mut stack = HashMap::<DateTime<Local>, f64>::new();
+
+    let mut seriess = Vec::new();
+    for (name, time_series) in time_seriess.iter().sorted_by_key(|pair| pair.0) {
+        let Some(&index) = indices.get(name) else {
+            debug!("Skipping unexpected time-series '{}'", name);
             continue;
-        }
-        for &(_date, value) in time_series {
-            min_y = min_y.min(value);
-            max_y = max_y.max(value);
-        }
+        };
+
+        let options = data.options(axis, name);
+
+        let points = time_series
+            .iter()
+            .map(|&(instant, value)| {
+                let (low, high) = match options.style {
+                    SeriesStyle::Line | SeriesStyle::Step => (value, value),
+                    SeriesStyle::Area | SeriesStyle::Bars => (options.baseline, value),
+                    SeriesStyle::StackedArea => {
+                        let low = stack.get(&instant).copied().unwrap_or(options.baseline);
+                        stack.insert(instant, low + value);
+                        (low, low + value)
+                    }
+                };
+                PlotPoint {
+                    instant,
+                    value,
+                    low,
+                    high,
+                }
+            })
+            .collect();
+
+        let envelope = if options.style == SeriesStyle::StackedArea {
+            Vec::new()
+        } else {
+            data.envelopes
+                .get(&(axis, name.clone()))
+                .into_iter()
+                .flatten()
+                .filter(|(_, min, max)| !min.is_nan() && !max.is_nan())
+                .map(|&(instant, min, max)| (instant.with_timezone(&Local), min, max))
+                .collect()
+        };
+
+        let comparison = data
+            .comparisons
+            .get(&(axis, name.clone()))
+            .map(|comparison| convert_time_series_to_local_time(comparison))
+            .unwrap_or_default();
+
+        let bar_slot = if options.style == SeriesStyle::Bars {
+            bar_position += 1;
+            (bar_position - 1, bar_count)
+        } else {
+            (0, 1)
+        };
+
+        seriess.push(PlotSeries {
+            name,
+            label: format!("{name}{label_suffix}"),
+            statistics: String::new(),
+            index,
+            options,
+            points,
+            envelope,
+            comparison,
+            bar_slot,
+            max_gap,
+            status: status
+                .filter(|_| matches!(options.style, SeriesStyle::Line | SeriesStyle::Step)),
+        });
+    }
+
+    seriess
+}
+
+/// Compute plot range
+///
+/// The range includes all values of time-series and all reference values.
+/// Logarithmic ranges only include positive values, and they are padded by a
+/// factor rather than by an offset.
+fn compute_range(
+    top_padding: Option<f64>,
+    seriess: &[PlotSeries],
+    references: &[f64],
+    logarithmic: bool,
+) -> (f64, f64) {
+    let points = seriess
+        .iter()
+        .flat_map(|series| &series.points)
+        .flat_map(|point| [point.low, point.high]);
+    let envelopes = seriess
+        .iter()
+        .flat_map(|series| &series.envelope)
+        .flat_map(|&(_, min, max)| [min, max]);
+    let comparisons = seriess
+        .iter()
+        .flat_map(|series| &series.comparison)
+        .map(|&(_, value)| value);
+
+    let (mut min_y, mut max_y) = points
+        .chain(envelopes)
+        .chain(comparisons)
+        .chain(references.iter().copied())
+        .filter(|&value| !logarithmic || value > 0.0)
+        .fold((f64::MAX, f64::MIN), |(min_y, max_y), value| {
+            (min_y.min(value), max_y.max(value))
+        });
+
+    // Fall back to a default range when there are no values, such as for an
+    // empty small multiple
+    if min_y > max_y {
+        (min_y, max_y) = if logarithmic { (1.0, 10.0) } else { (0.0, 1.0) };
     }
 
     // Increase maximal Y range to make space for the legend
     let top_padding = top_padding.unwrap_or(0.0);
-    max_y += top_padding * (max_y - min_y);
+    if logarithmic {
+        max_y *= (max_y / min_y).powf(top_padding);
+    } else {
+        max_y += top_padding * (max_y - min_y);
+    }
 
     debug!("Plot Y range: [{}, {}]", min_y, max_y);
 
@@ -193,12 +823,9 @@ fn compute_range<S>(
 }
 
 /// Convert time-series to local time
-fn convert_time_seriess_to_local_time<S>(
-    time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>, S>,
-) -> HashMap<String, Vec<(DateTime<Local>, f64)>, S>
-where
-    S: BuildHasher + Default,
-{
+fn convert_time_seriess_to_local_time(
+    time_seriess: &HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+) -> HashMap<String, Vec<(DateTime<Local>, f64)>> {
     time_seriess
         .iter()
         .map(|(name, time_series)| (name.clone(), convert_time_series_to_local_time(time_series)))
@@ -216,22 +843,53 @@ fn convert_time_series_to_local_time(
 }
 
 /// Create a chart context
-fn create_chart_context<'a, DB: DrawingBackend + 'a, S>(
-    top_padding: Option<f64>,
-    min_y_range: Option<f64>,
+///
+/// Space is reserved on the right for legends outside the plot.
+fn create_chart_context<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
     (min_x, max_x): (DateTime<Local>, DateTime<Local>),
-    indices: &HashMap<String, usize>,
+    (has_secondary_axis, legend_width): (bool, i32),
     root: &'a DrawingArea<DB, Shift>,
-    time_seriess: &HashMap<String, Vec<(DateTime<Local>, f64)>, S>,
-) -> Result<ChartContextAlias<'a, DB>, Error>
-where
-    S: BuildHasher,
-{
+    seriess: &[PlotSeries],
+    references: &[f64],
+) -> Result<ChartContextAlias<'a, DB>, Error> {
     debug!("Creating chart");
 
-    let (mut min_y, mut max_y) = compute_range(top_padding, indices, time_seriess);
+    let logarithmic = trend.log_scale.unwrap_or(false);
+    let (min_y, max_y) = primary_range(trend, seriess, references)?;
+
+    let mut builder = ChartBuilder::on(root);
+    builder
+        .margin(5)
+        .x_label_area_size(20)
+        .y_label_area_size(50);
+    if trend.legend_position == Some(LegendPosition::Outside) && !trend.hide_legend.unwrap_or(false)
+    {
+        builder.margin_right(legend_width + 2 * LEGEND_SPACING);
+    }
+    if has_secondary_axis {
+        builder.right_y_label_area_size(50);
+    }
+    let chart =
+        builder.build_cartesian_2d(min_x..max_x, ValueCoord::new(min_y..max_y, logarithmic))?;
+
+    Ok(chart)
+}
+
+/// Compute the range of the primary axis
+///
+/// The range is widened to the minimal Y range, and then replaced by the
+/// fixed bounds, if any.
+fn primary_range(
+    trend: &TrendConfiguration,
+    seriess: &[PlotSeries],
+    references: &[f64],
+) -> Result<(f64, f64), Error> {
+    let (fixed_min_y, fixed_max_y) = trend.fixed_y_range()?;
+    let logarithmic = trend.log_scale.unwrap_or(false);
+    let (mut min_y, mut max_y) = compute_range(trend.top_padding, seriess, references, logarithmic);
 
-    if let Some(min_y_range) = min_y_range {
+    if let Some(min_y_range) = trend.min_y_range.filter(|_| !logarithmic) {
         let increment = min_y_range / 10.0;
         while max_y - min_y < min_y_range {
             min_y -= increment;
@@ -239,62 +897,57 @@ fn create_chart_context<'a, DB: DrawingBackend + 'a, S>(
         }
     }
 
-    let chart = ChartBuilder::on(root)
-        .margin(5)
-        .x_label_area_size(20)
-        .y_label_area_size(50)
-        .build_cartesian_2d(min_x..max_x, min_y..max_y)?;
+    Ok((fixed_min_y.unwrap_or(min_y), fixed_max_y.unwrap_or(max_y)))
+}
 
-    Ok(chart)
+/// Compute the range of the secondary axis
+///
+/// The range is fixed if set in the secondary axis configuration.
+fn secondary_range(trend: &TrendConfiguration, seriess: &[PlotSeries]) -> (f64, f64) {
+    trend
+        .secondary_axis
+        .as_ref()
+        .and_then(|secondary_axis| secondary_axis.range)
+        .unwrap_or_else(|| {
+            compute_range(
+                trend.top_padding,
+                seriess,
+                &trend.reference_values(Axis::Secondary),
+                false,
+            )
+        })
 }
 
 /// Plot a time-series
-fn plot_time_series<'a, DB: DrawingBackend + 'a>(
+fn plot_series<'a, DB: DrawingBackend + 'a>(
     trend: &TrendConfiguration,
-    indices: &HashMap<String, usize>,
     style: &'a StyleConfiguration,
     root: &DrawingArea<DB, Shift>,
     chart: &mut ChartContextAlias<'a, DB>,
-    name: &str,
-    time_series: &[(DateTime<Local>, f64)],
+    period: Duration,
+    series: &PlotSeries,
+    mapping: Option<AxisMapping>,
 ) -> Result<(), Error> {
     let value_font = (style.font_name.as_str(), 8.0 * style.font_scale)
         .into_font()
         .color(&style.system_palette.pick(SystemColor::Foreground))
         .pos(Pos::new(HPos::Right, VPos::Bottom));
 
-    let index = if let Some(index) = indices.get(name) {
-        *index
-    } else {
-        debug!("Unexpected tag value {}", name);
-        return Ok(());
-    };
-
-    chart
-        .draw_series(LineSeries::new(
-            time_series.iter().map(|&(dt, value)| (dt, value)),
-            style.series_palette.pick(index).stroke_width(3),
-        ))?
-        .label(name)
-        .legend(move |(x, y)| {
-            PathElement::new(
-                vec![(x, y), (x + 20, y)],
-                style.series_palette.pick(index).stroke_width(2),
-            )
-        });
+    plot_shape(style, chart, period, series, mapping)?;
 
-    if style.draw_markers.unwrap_or(false) {
-        chart.draw_series(time_series.iter().map(|&(dt, value)| {
-            Circle::new((dt, value), 3, style.series_palette.pick(index).filled())
-        }))?;
-    }
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
 
     if trend.draw_last_value.unwrap_or(false) {
-        if let Some(last_reading) = time_series.last() {
-            let last_value = last_reading.1;
-            let last_value_text = format!("{0:.1$}", last_value, trend.precision.unwrap_or(0),);
+        if let Some(last_point) = series.points.last() {
+            let precision = mapping
+                .and(trend.secondary_axis.as_ref())
+                .and_then(|secondary_axis| secondary_axis.precision)
+                .or(trend.precision)
+                .unwrap_or(0);
+            let last_value_text = format!("{0:.1$}", last_point.value, precision);
 
-            let last_value_coordinates = chart.backend_coord(last_reading);
+            let last_value_coordinates =
+                chart.backend_coord(&(last_point.instant, map(last_point.high)));
 
             root.draw(&Text::new(
                 last_value_text,
@@ -302,24 +955,621 @@ fn plot_time_series<'a, DB: DrawingBackend + 'a>(
                 &value_font,
             ))?;
         } else {
-            warn!("Empty time-series '{}', cannot draw last value", name);
+            warn!(
+                "Empty time-series '{}', cannot draw last value",
+                series.name
+            );
         }
     }
 
     Ok(())
 }
 
-/// Draw chart axes
-fn draw_axes<'a, DB: DrawingBackend + 'a>(
-    trend: &TrendConfiguration,
+/// Plot the shape of a time-series according to its style
+///
+/// Markers are drawn on top of the shape if enabled in the style.
+fn plot_shape<'a, DB: DrawingBackend + 'a>(
     style: &'a StyleConfiguration,
     chart: &mut ChartContextAlias<'a, DB>,
+    period: Duration,
+    series: &PlotSeries,
+    mapping: Option<AxisMapping>,
 ) -> Result<(), Error> {
-    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
-        .into_font()
-        .color(&style.system_palette.pick(SystemColor::Foreground));
+    let color = style.series_palette.pick(series.index);
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+    let dashed = mapping.is_some() || series.options.forecast;
+    let symbol = series.symbol(style, mapping.is_some());
 
-    let ylabel = match (trend.ylabel.as_ref(), trend.yunit.as_ref()) {
+    match series.options.style {
+        SeriesStyle::Line | SeriesStyle::Step => {
+            plot_line(chart, series, color, dashed, mapping)?;
+        }
+        SeriesStyle::Area | SeriesStyle::StackedArea => {
+            let fill = color.mix(AREA_OPACITY).filled();
+            let segments = split_at_gaps(&series.points, |point| point.instant, series.max_gap);
+            for (position, segment) in segments.into_iter().enumerate() {
+                let highs: Vec<(DateTime<Local>, f64)> = segment
+                    .iter()
+                    .map(|point| (point.instant, map(point.high)))
+                    .collect();
+                let outline: Vec<(DateTime<Local>, f64)> = highs
+                    .iter()
+                    .copied()
+                    .chain(
+                        segment
+                            .iter()
+                            .rev()
+                            .map(|point| (point.instant, map(point.low))),
+                    )
+                    .collect();
+                let annotation = chart.draw_series(
+                    std::iter::once(Polygon::new(outline, fill)).filter(|_| !highs.is_empty()),
+                )?;
+                if position == 0 {
+                    annotation
+                        .label(series.legend_label())
+                        .legend(move |coord| symbol.element(coord));
+                }
+                draw_line(chart, highs, color, dashed, None)?;
+            }
+        }
+        SeriesStyle::Bars => {
+            let fill = color.filled();
+            chart
+                .draw_series(series.points.iter().map(|point| {
+                    let (start, end) = bar_span(point.instant, period, series.bar_slot);
+                    Rectangle::new([(start, map(point.high)), (end, map(point.low))], fill)
+                }))?
+                .label(series.legend_label())
+                .legend(move |coord| symbol.element(coord));
+        }
+    }
+
+    if style.draw_markers.unwrap_or(false) {
+        chart.draw_series(
+            series
+                .points
+                .iter()
+                .map(|point| Circle::new((point.instant, map(point.high)), 3, color.filled())),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Plot a time-series as a line or as steps
+///
+/// Lines are broken at gaps, and only the first segment appears in the legend.
+fn plot_line<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContextAlias<'a, DB>,
+    series: &PlotSeries,
+    color: RGBColor,
+    dashed: bool,
+    mapping: Option<AxisMapping>,
+) -> Result<(), Error> {
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+    let line: Vec<(DateTime<Local>, f64)> = series
+        .points
+        .iter()
+        .map(|point| (point.instant, point.value))
+        .collect();
+    let segments = split_at_gaps(&line, |&(instant, _)| instant, series.max_gap);
+    for (position, segment) in segments.into_iter().enumerate() {
+        let segment = if series.options.style == SeriesStyle::Step {
+            step_points(segment)
+        } else {
+            segment.to_vec()
+        };
+        let mapped_segment = segment
+            .iter()
+            .map(|&(instant, value)| (instant, map(value)));
+        let label = (position == 0).then(|| series.legend_label());
+        draw_line(
+            chart,
+            mapped_segment.collect(),
+            color,
+            dashed,
+            label.as_deref(),
+        )?;
+        if let Some(target_range) = series.status {
+            draw_status_segments(chart, &segment, target_range, mapping, dashed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Plot the envelope between minimal and maximal values of a time-series
+fn plot_envelope<'a, DB: DrawingBackend + 'a>(
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    series: &PlotSeries,
+    mapping: Option<AxisMapping>,
+) -> Result<(), Error> {
+    if series.envelope.is_empty() {
+        return Ok(());
+    }
+
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+    let fill = style
+        .series_palette
+        .pick(series.index)
+        .mix(ENVELOPE_OPACITY)
+        .filled();
+
+    let segments = split_at_gaps(&series.envelope, |&(instant, _, _)| instant, series.max_gap);
+    chart.draw_series(segments.into_iter().map(|segment| {
+        let outline: Vec<(DateTime<Local>, f64)> = segment
+            .iter()
+            .map(|&(instant, _, max)| (instant, map(max)))
+            .chain(
+                segment
+                    .iter()
+                    .rev()
+                    .map(|&(instant, min, _)| (instant, map(min))),
+            )
+            .collect();
+        Polygon::new(outline, fill)
+    }))?;
+
+    Ok(())
+}
+
+/// Plot the values of a time-series over the comparison period
+///
+/// Comparisons are drawn as faded dashed lines, or as steps for step series.
+fn plot_comparison<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    series: &PlotSeries,
+    mapping: Option<AxisMapping>,
+) -> Result<(), Error> {
+    if series.comparison.is_empty() {
+        return Ok(());
+    }
+
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+    let segments = split_at_gaps(&series.comparison, |&(instant, _)| instant, series.max_gap);
+    let lines = segments.into_iter().map(|segment| {
+        let segment = if series.options.style == SeriesStyle::Step {
+            step_points(segment)
+        } else {
+            segment.to_vec()
+        };
+        segment
+            .into_iter()
+            .map(|(instant, value)| (instant, map(value)))
+            .collect::<Vec<(DateTime<Local>, f64)>>()
+    });
+
+    let color = style
+        .series_palette
+        .pick(series.index)
+        .mix(COMPARISON_OPACITY);
+    let label = comparison_label(trend, series);
+
+    chart
+        .draw_series(lines.map(|line| {
+            DashedPath::new(line, SECONDARY_DASH, SECONDARY_GAP, color.stroke_width(2))
+        }))?
+        .label(label)
+        .legend(move |coord| LegendSymbol::DashedLine(color).element(coord));
+
+    Ok(())
+}
+
+/// Draw line segments outside a target range with the status colormap
+///
+/// Segments are colored according to their largest deviation from the range.
+fn draw_status_segments<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContextAlias<'a, DB>,
+    line: &[(DateTime<Local>, f64)],
+    target_range: &TargetRangeConfiguration,
+    mapping: Option<AxisMapping>,
+    dashed: bool,
+) -> Result<(), Error> {
+    let max_deviation = target_range
+        .max_deviation
+        .unwrap_or(target_range.to - target_range.from);
+    let colormap =
+        Colormap::new_with_bounds(Some(ColormapType::Status).as_ref(), 0.0, max_deviation)?;
+
+    let map = |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+
+    for (&(start, start_value), &(end, end_value)) in line.iter().tuple_windows() {
+        let deviation = target_range
+            .deviation(start_value)
+            .max(target_range.deviation(end_value));
+        if deviation > 0.0 {
+            let color = colormap.get_color(deviation.min(max_deviation));
+            let segment = vec![(start, map(start_value)), (end, map(end_value))];
+            draw_line(chart, segment, color, dashed, None)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw target ranges as shaded bands
+fn draw_target_ranges<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    mapping: Option<AxisMapping>,
+) -> Result<(), Error> {
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground))
+        .pos(Pos::new(HPos::Left, VPos::Top));
+
+    let x_range = chart.x_range();
+
+    for target_range in trend.target_ranges.iter().flatten() {
+        let Some(map) = axis_mapping(target_range.axis, mapping) else {
+            warn!("Skipping target range on missing secondary axis");
+            continue;
+        };
+
+        let color = target_range.color.map_or_else(
+            || style.system_palette.pick(SystemColor::Middle),
+            |color| color.0,
+        );
+        let top_left = (x_range.start, map(target_range.to.max(target_range.from)));
+        let bottom_right = (x_range.end, map(target_range.to.min(target_range.from)));
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [top_left, bottom_right],
+            color.mix(TARGET_RANGE_OPACITY).filled(),
+        )))?;
+
+        if let Some(ref label) = target_range.label {
+            chart.draw_series(std::iter::once(
+                EmptyElement::at(top_left) + Text::new(label.clone(), (2, 2), label_font.clone()),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw thresholds as horizontal dashed lines
+fn draw_thresholds<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    mapping: Option<AxisMapping>,
+) -> Result<(), Error> {
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground))
+        .pos(Pos::new(HPos::Right, VPos::Bottom));
+
+    let x_range = chart.x_range();
+
+    for threshold in trend.thresholds.iter().flatten() {
+        let Some(map) = axis_mapping(threshold.axis, mapping) else {
+            warn!("Skipping threshold on missing secondary axis");
+            continue;
+        };
+
+        let color = threshold.color.map_or_else(
+            || style.system_palette.pick(SystemColor::Foreground),
+            |color| color.0,
+        );
+        let y = map(threshold.value);
+        chart.draw_series(std::iter::once(DashedPath::new(
+            vec![(x_range.start, y), (x_range.end, y)],
+            THRESHOLD_DASH,
+            THRESHOLD_DASH,
+            color.stroke_width(1),
+        )))?;
+
+        if let Some(ref label) = threshold.label {
+            chart.draw_series(std::iter::once(
+                EmptyElement::at((x_range.end, y))
+                    + Text::new(label.clone(), (-2, -1), label_font.clone()),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw a vertical marker at the current time
+///
+/// The current time is `how_far_ahead` before the end of the chart.
+fn draw_now_marker<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+) -> Result<(), Error> {
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground))
+        .pos(Pos::new(HPos::Left, VPos::Top));
+
+    let ahead = trend
+        .how_far_ahead
+        .as_ref()
+        .and_then(|how_far_ahead| Duration::from_std(how_far_ahead.duration.unsigned_abs()).ok())
+        .unwrap_or_else(Duration::zero);
+
+    let x_range = chart.x_range();
+    let y_range = chart.y_range();
+    let now = x_range.end - ahead;
+
+    chart.draw_series(std::iter::once(DashedPath::new(
+        vec![(now, y_range.start), (now, y_range.end)],
+        NOW_DASH,
+        NOW_DASH,
+        style
+            .system_palette
+            .pick(SystemColor::Foreground)
+            .stroke_width(1),
+    )))?;
+    chart.draw_series(std::iter::once(
+        EmptyElement::at((now, y_range.end)) + Text::new("now", (2, 2), label_font),
+    ))?;
+
+    Ok(())
+}
+
+/// Draw event markers as vertical lines
+fn draw_annotation_markers<'a, DB: DrawingBackend + 'a>(
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    annotations: &[Annotation],
+) -> Result<(), Error> {
+    let y_range = chart.y_range();
+
+    for (instant, annotation) in visible_annotations(chart, annotations) {
+        let color = annotation
+            .color
+            .unwrap_or_else(|| style.system_palette.pick(SystemColor::Foreground));
+        chart.draw_series(std::iter::once(DashedPath::new(
+            vec![(instant, y_range.start), (instant, y_range.end)],
+            ANNOTATION_DASH,
+            ANNOTATION_DASH,
+            color.stroke_width(1),
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Draw labels of event markers
+///
+/// Labels are stacked in rows below the top of the chart, and each label is
+/// placed in the first row where it does not overlap other labels.
+/// Labels are placed on the left of markers near the right side of the chart.
+fn draw_annotation_labels<'a, DB: DrawingBackend + 'a>(
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    annotations: &[Annotation],
+) -> Result<(), Error> {
+    let font = (style.font_name.as_str(), 8.0 * style.font_scale).into_font();
+    let background = style.system_palette.pick(SystemColor::Background).filled();
+    let y_range = chart.y_range();
+    let (pixel_range, _) = chart.plotting_area().get_pixel_range();
+
+    let mut row_ends: Vec<i32> = Vec::new();
+
+    for (instant, annotation) in visible_annotations(chart, annotations) {
+        let color = annotation
+            .color
+            .unwrap_or_else(|| style.system_palette.pick(SystemColor::Foreground));
+
+        let text = shorten_label(&annotation.text);
+        let (width, height) = font.box_size(&text).map_err(|_| Error::Font)?;
+        let (width, height) = (i32::try_from(width)?, i32::try_from(height)?);
+
+        let x = chart.backend_coord(&(instant, y_range.end)).0;
+        let (left, right, dx) = if x + width + 2 > pixel_range.end {
+            (x - width - 2, x, -width - 2)
+        } else {
+            (x, x + width + 2, 2)
+        };
+
+        let row = row_ends
+            .iter()
+            .position(|&end| end < left)
+            .unwrap_or(row_ends.len());
+        if let Some(end) = row_ends.get_mut(row) {
+            *end = right + ANNOTATION_SPACING;
+        } else {
+            row_ends.push(right + ANNOTATION_SPACING);
+        }
+        let dy = 2 + i32::try_from(row)? * (height + 2);
+
+        let label_font = font
+            .clone()
+            .color(&color)
+            .pos(Pos::new(HPos::Left, VPos::Top));
+        chart.draw_series(std::iter::once(
+            EmptyElement::at((instant, y_range.end))
+                + Rectangle::new([(dx, dy), (dx + width, dy + height)], background)
+                + Text::new(text, (dx, dy), label_font),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Return event markers within the chart, sorted by instant
+fn visible_annotations<'b, DB: DrawingBackend>(
+    chart: &ChartContextAlias<'_, DB>,
+    annotations: &'b [Annotation],
+) -> Vec<(DateTime<Local>, &'b Annotation)> {
+    let x_range = chart.x_range();
+    annotations
+        .iter()
+        .map(|annotation| (annotation.instant.with_timezone(&Local), annotation))
+        .filter(|(instant, _)| x_range.contains(instant) || *instant == x_range.end)
+        .sorted_by_key(|&(instant, _)| instant)
+        .collect()
+}
+
+/// Shorten a label to at most `ANNOTATION_LENGTH` characters
+fn shorten_label(text: &str) -> String {
+    if text.chars().count() > ANNOTATION_LENGTH {
+        let prefix: String = text.chars().take(ANNOTATION_LENGTH - 3).collect();
+        format!("{}...", prefix.trim_end())
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Return a function mapping values on an axis to the primary axis
+///
+/// Return nothing for the secondary axis when the chart has none.
+fn axis_mapping(axis: Option<Axis>, mapping: Option<AxisMapping>) -> Option<impl Fn(f64) -> f64> {
+    let mapping = match axis.unwrap_or_default() {
+        Axis::Primary => None,
+        Axis::Secondary => Some(mapping?),
+    };
+    Some(move |value: f64| mapping.map_or(value, |mapping| mapping.map(value)))
+}
+
+/// Draw a line through points
+///
+/// Dashed lines are used for series on the secondary axis and for forecasts.
+/// Lines without label do not appear in the legend.
+fn draw_line<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContextAlias<'a, DB>,
+    points: Vec<(DateTime<Local>, f64)>,
+    color: RGBColor,
+    dashed: bool,
+    label: Option<&str>,
+) -> Result<(), Error> {
+    if dashed {
+        let annotation = chart.draw_series(std::iter::once(DashedPath::new(
+            points,
+            SECONDARY_DASH,
+            SECONDARY_GAP,
+            color.stroke_width(3),
+        )))?;
+        if let Some(label) = label {
+            annotation
+                .label(label)
+                .legend(move |coord| LegendSymbol::DashedLine(color.to_rgba()).element(coord));
+        }
+    } else {
+        let annotation = chart.draw_series(LineSeries::new(points, color.stroke_width(3)))?;
+        if let Some(label) = label {
+            annotation
+                .label(label)
+                .legend(move |coord| LegendSymbol::Line(color.to_rgba()).element(coord));
+        }
+    }
+
+    Ok(())
+}
+
+/// Split points where the interval between consecutive points is too long
+///
+/// Points are not split without a maximal interval.
+/// An empty slice is a single empty segment.
+fn split_at_gaps<T>(
+    points: &[T],
+    instant: impl Fn(&T) -> DateTime<Local>,
+    max_gap: Option<Duration>,
+) -> Vec<&[T]> {
+    let Some(max_gap) = max_gap else {
+        return vec![points];
+    };
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (index, (previous, next)) in points.iter().tuple_windows().enumerate() {
+        if instant(next) - instant(previous) > max_gap {
+            segments.push(&points[start..=index]);
+            start = index + 1;
+        }
+    }
+    segments.push(&points[start..]);
+    segments
+}
+
+/// Shade the intervals without data of a time-series
+fn draw_gaps<'a, DB: DrawingBackend + 'a>(
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+    series: &PlotSeries,
+) -> Result<(), Error> {
+    let Some(max_gap) = series.max_gap else {
+        return Ok(());
+    };
+
+    let y_range = chart.y_range();
+    let fill = style
+        .system_palette
+        .pick(SystemColor::Middle)
+        .mix(GAP_OPACITY)
+        .filled();
+
+    chart.draw_series(
+        series
+            .points
+            .iter()
+            .tuple_windows()
+            .filter(|(previous, next)| next.instant - previous.instant > max_gap)
+            .map(|(previous, next)| {
+                Rectangle::new(
+                    [
+                        (previous.instant, y_range.end),
+                        (next.instant, y_range.start),
+                    ],
+                    fill,
+                )
+            }),
+    )?;
+
+    Ok(())
+}
+
+/// Convert points to a step line
+///
+/// Each value is held until the instant of the next point.
+fn step_points(points: &[(DateTime<Local>, f64)]) -> Vec<(DateTime<Local>, f64)> {
+    let mut steps = Vec::with_capacity(points.len() * 2);
+    for (&(instant, value), &(next_instant, _)) in points.iter().tuple_windows() {
+        steps.push((instant, value));
+        steps.push((next_instant, value));
+    }
+    if let Some(&last) = points.last() {
+        steps.push(last);
+    }
+    steps
+}
+
+/// Compute the horizontal span of a bar
+///
+/// Bars span the period starting at their instant, leaving a small gap on
+/// both sides.
+/// Bars of different series in the same period are placed side by side.
+fn bar_span(
+    instant: DateTime<Local>,
+    period: Duration,
+    (position, count): (usize, usize),
+) -> (DateTime<Local>, DateTime<Local>) {
+    let count = i32::try_from(count).unwrap_or(1).max(1);
+    let position = i32::try_from(position).unwrap_or(0);
+    let start = instant + period * (count + 8 * position) / (10 * count);
+    let end = instant + period * (count + 8 * (position + 1)) / (10 * count);
+    (start, end)
+}
+
+/// Draw chart axes
+fn draw_axes<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    chart: &mut ChartContextAlias<'a, DB>,
+) -> Result<(), Error> {
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground));
+
+    let ylabel = match (trend.ylabel.as_ref(), trend.yunit.as_ref()) {
         (Some(ylabel), Some(yunit)) => format!("{ylabel} [{yunit}]"),
         (Some(ylabel), None) => ylabel.clone(),
         (None, _) => String::new(),
@@ -347,24 +1597,353 @@ fn draw_axes<'a, DB: DrawingBackend + 'a>(
     Ok(())
 }
 
+/// Draw secondary axis
+fn draw_secondary_axis<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    secondary_axis: Option<&SecondaryAxisConfiguration>,
+    style: &'a StyleConfiguration,
+    chart: &mut DualChartContextAlias<'a, DB>,
+) -> Result<(), Error> {
+    let label_font = (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground));
+
+    let ylabel = match (
+        secondary_axis.and_then(|secondary_axis| secondary_axis.ylabel.as_ref()),
+        secondary_axis.and_then(|secondary_axis| secondary_axis.yunit.as_ref()),
+    ) {
+        (Some(ylabel), Some(yunit)) => format!("{ylabel} [{yunit}]"),
+        (Some(ylabel), None) => ylabel.clone(),
+        (None, _) => String::new(),
+    };
+
+    let precision = secondary_axis
+        .and_then(|secondary_axis| secondary_axis.precision)
+        .or(trend.precision)
+        .unwrap_or(0);
+
+    chart
+        .configure_secondary_axes()
+        .axis_style(style.system_palette.pick(SystemColor::Foreground))
+        .y_labels(trend.max_y_ticks.unwrap_or(5))
+        .y_label_formatter(&|value| format!("{value:.precision$}"))
+        .y_desc(ylabel)
+        .label_style(label_font)
+        .draw()?;
+
+    Ok(())
+}
+
 /// Draw chart legend
+///
+/// Automatically placed legends are drawn in the corner of the plot that
+/// covers the fewest data points, preferring upper corners and left corners.
+/// Legends outside the plot are drawn on the right of the chart.
 fn draw_legend<'a, DB: DrawingBackend + 'a>(
+    trend: &TrendConfiguration,
+    style: &'a StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
     chart: &mut ChartContextAlias<'a, DB>,
-    style: &StyleConfiguration,
+    (legend_width, legend_height): (i32, i32),
+    (seriess, secondary): (&[PlotSeries], Option<(&[PlotSeries], AxisMapping)>),
 ) -> Result<(), Error> {
     debug!("Drawing legend");
 
-    let legend_font = (style.font_name.as_str(), 8.0 * style.font_scale)
-        .into_font()
-        .color(&style.system_palette.pick(SystemColor::Foreground));
+    if trend.legend_position == Some(LegendPosition::Outside) {
+        let secondary_seriess =
+            secondary.map_or(&[][..], |(secondary_seriess, _)| secondary_seriess);
+        let entries = legend_entries(trend, style, (seriess, secondary_seriess));
+        return draw_outside_legend(style, root, &entries, (legend_width, legend_height));
+    }
+
+    let (x_range, y_range) = chart.plotting_area().get_pixel_range();
+    let corner = |position: LegendPosition| match position {
+        LegendPosition::UpperRight => (
+            x_range.end - legend_width - LEGEND_SPACING,
+            y_range.start + LEGEND_SPACING,
+        ),
+        LegendPosition::LowerLeft => (
+            x_range.start + LEGEND_SPACING,
+            y_range.end - legend_height - LEGEND_SPACING,
+        ),
+        LegendPosition::LowerRight => (
+            x_range.end - legend_width - LEGEND_SPACING,
+            y_range.end - legend_height - LEGEND_SPACING,
+        ),
+        LegendPosition::UpperLeft | LegendPosition::Auto | LegendPosition::Outside => (
+            x_range.start + LEGEND_SPACING,
+            y_range.start + LEGEND_SPACING,
+        ),
+    };
+
+    let position = match trend.legend_position.unwrap_or_default() {
+        LegendPosition::UpperLeft | LegendPosition::Outside => SeriesLabelPosition::UpperLeft,
+        LegendPosition::UpperRight => SeriesLabelPosition::UpperRight,
+        LegendPosition::LowerLeft => SeriesLabelPosition::LowerLeft,
+        LegendPosition::LowerRight => SeriesLabelPosition::LowerRight,
+        LegendPosition::Auto => {
+            let coordinates = data_coordinates(chart, seriess, secondary);
+            let (x, y) = [
+                LegendPosition::UpperLeft,
+                LegendPosition::UpperRight,
+                LegendPosition::LowerLeft,
+                LegendPosition::LowerRight,
+            ]
+            .into_iter()
+            .map(corner)
+            .min_by_key(|&(x, y)| {
+                coordinates
+                    .iter()
+                    .filter(|&&(point_x, point_y)| {
+                        (x..=x + legend_width).contains(&point_x)
+                            && (y..=y + legend_height).contains(&point_y)
+                    })
+                    .count()
+            })
+            .unwrap_or_else(|| corner(LegendPosition::UpperLeft));
+            SeriesLabelPosition::Coordinate(x - x_range.start, y - y_range.start)
+        }
+    };
 
     chart
         .configure_series_labels()
         .background_style(style.system_palette.pick(SystemColor::LightBackground))
         .border_style(style.system_palette.pick(SystemColor::LightForeground))
-        .position(SeriesLabelPosition::UpperLeft)
-        .label_font(legend_font)
+        .position(position)
+        .legend_area_size(LEGEND_SYMBOL_WIDTH)
+        .margin(LEGEND_MARGIN)
+        .label_font(legend_font(style))
         .draw()?;
 
     Ok(())
 }
+
+/// Create the font of legend labels
+fn legend_font(style: &StyleConfiguration) -> TextStyle<'_> {
+    (style.font_name.as_str(), 8.0 * style.font_scale)
+        .into_font()
+        .color(&style.system_palette.pick(SystemColor::Foreground))
+}
+
+/// Collect the labels and symbols in the legend
+///
+/// Each time-series is followed by its comparison period, if any.
+fn legend_entries(
+    trend: &TrendConfiguration,
+    style: &StyleConfiguration,
+    (seriess, secondary_seriess): (&[PlotSeries], &[PlotSeries]),
+) -> Vec<(String, LegendSymbol)> {
+    let all_seriess = seriess
+        .iter()
+        .map(|series| (series, false))
+        .chain(secondary_seriess.iter().map(|series| (series, true)));
+    all_seriess
+        .flat_map(|(series, secondary)| {
+            let comparison =
+                (trend.compare_with.is_some() && !series.comparison.is_empty()).then(|| {
+                    let color = style
+                        .series_palette
+                        .pick(series.index)
+                        .mix(COMPARISON_OPACITY);
+                    (
+                        comparison_label(trend, series),
+                        LegendSymbol::DashedLine(color),
+                    )
+                });
+            std::iter::once((series.legend_label(), series.symbol(style, secondary)))
+                .chain(comparison)
+        })
+        .collect()
+}
+
+/// Create the text of legend labels at a position
+fn legend_text<'b>(
+    font: &TextStyle<'b>,
+    entries: &'b [(String, LegendSymbol)],
+    position: (i32, i32),
+) -> MultiLineText<'b, (i32, i32), &'b str> {
+    let mut text = MultiLineText::new(position, font.clone());
+    for (label, _) in entries {
+        text.push_line(label.as_str());
+    }
+    text
+}
+
+/// Estimate the size of the legend in pixels
+fn estimate_legend_size(
+    font: &TextStyle,
+    entries: &[(String, LegendSymbol)],
+) -> Result<(i32, i32), Error> {
+    let (width, height) = legend_text(font, entries, (0, 0))
+        .estimate_dimension()
+        .map_err(|_| Error::Font)?;
+    let margin = i32::try_from(LEGEND_MARGIN)?;
+    Ok((
+        width + i32::try_from(LEGEND_SYMBOL_WIDTH)? + 2 * margin,
+        height + 2 * margin,
+    ))
+}
+
+/// Draw the legend on the right of the chart, outside of the plot
+///
+/// The legend is drawn manually, because series labels cannot be drawn
+/// outside of the plotting area.
+fn draw_outside_legend<DB: DrawingBackend>(
+    style: &StyleConfiguration,
+    root: &DrawingArea<DB, Shift>,
+    entries: &[(String, LegendSymbol)],
+    (legend_width, legend_height): (i32, i32),
+) -> Result<(), Error> {
+    let (width, _height) = root.dim_in_pixel();
+    let x = i32::try_from(width)? - legend_width - LEGEND_SPACING;
+    let y = LEGEND_SPACING;
+    let margin = i32::try_from(LEGEND_MARGIN)?;
+    let symbol_width = i32::try_from(LEGEND_SYMBOL_WIDTH)?;
+
+    let corners = [(x, y), (x + legend_width, y + legend_height)];
+    root.draw(&Rectangle::new(
+        corners,
+        style
+            .system_palette
+            .pick(SystemColor::LightBackground)
+            .filled(),
+    ))?;
+    root.draw(&Rectangle::new(
+        corners,
+        style.system_palette.pick(SystemColor::LightForeground),
+    ))?;
+
+    let font = legend_font(style);
+    let text = legend_text(&font, entries, (x + symbol_width + margin, y + margin));
+    root.draw(&text)?;
+
+    let layout = text.compute_line_layout().map_err(|_| Error::Font)?;
+    for (((_, top), (_, bottom)), &(_, symbol)) in layout.into_iter().zip(entries) {
+        root.draw(&symbol.element((x + margin, top + (bottom - top) / 2)))?;
+    }
+
+    Ok(())
+}
+
+/// Compute the pixel coordinates of all plotted values
+fn data_coordinates<'a, DB: DrawingBackend + 'a>(
+    chart: &ChartContextAlias<'a, DB>,
+    seriess: &[PlotSeries],
+    secondary: Option<(&[PlotSeries], AxisMapping)>,
+) -> Vec<(i32, i32)> {
+    let all_seriess =
+        seriess
+            .iter()
+            .map(|series| (series, None))
+            .chain(
+                secondary
+                    .into_iter()
+                    .flat_map(|(secondary_seriess, mapping)| {
+                        secondary_seriess
+                            .iter()
+                            .map(move |series| (series, Some(mapping)))
+                    }),
+            );
+
+    all_seriess
+        .flat_map(|(series, mapping)| {
+            let map = move |value: f64| mapping.map_or(value, |mapping| mapping.map(value));
+            let points = series.points.iter().flat_map(move |point| {
+                [
+                    (point.instant, map(point.low)),
+                    (point.instant, map(point.high)),
+                ]
+            });
+            let envelope = series
+                .envelope
+                .iter()
+                .flat_map(move |&(instant, min, max)| [(instant, map(min)), (instant, map(max))]);
+            let comparison = series
+                .comparison
+                .iter()
+                .map(move |&(instant, value)| (instant, map(value)));
+            points.chain(envelope).chain(comparison)
+        })
+        .map(|coordinate| chart.backend_coord(&coordinate))
+        .collect()
+}
+
+/// Describe statistics of time-series in their legend labels
+///
+/// Statistics are computed over the original values, and they are formatted
+/// with the precision and unit of the axis.
+fn describe_statistics(trend: &TrendConfiguration, axis: Axis, seriess: &mut [PlotSeries]) {
+    let Some(statistics) = trend
+        .legend_statistics
+        .as_deref()
+        .filter(|statistics| !statistics.is_empty())
+    else {
+        return;
+    };
+
+    let (precision, unit) = axis_format(trend, axis);
+
+    for series in seriess {
+        let values: Vec<f64> = series
+            .points
+            .iter()
+            .map(|point| point.value)
+            .filter(|value| !value.is_nan())
+            .collect();
+        let (Some(&last), Ok(count)) = (values.last(), u32::try_from(values.len())) else {
+            continue;
+        };
+
+        let description = statistics
+            .iter()
+            .map(|statistic| {
+                let (name, value) = match *statistic {
+                    LegendStatistic::Min => {
+                        ("min", values.iter().copied().fold(f64::MAX, f64::min))
+                    }
+                    LegendStatistic::Mean => {
+                        ("mean", values.iter().sum::<f64>() / f64::from(count))
+                    }
+                    LegendStatistic::Max => {
+                        ("max", values.iter().copied().fold(f64::MIN, f64::max))
+                    }
+                    LegendStatistic::Last => ("last", last),
+                };
+                format!("{name} {value:.precision$}")
+            })
+            .join(", ");
+        let unit = unit.map(|unit| format!(" {unit}")).unwrap_or_default();
+        series.statistics = format!(" ({description}{unit})");
+    }
+}
+
+/// Return the precision and unit of values on an axis
+///
+/// Values on the secondary axis use the chart precision unless the axis sets
+/// its own.
+fn axis_format(trend: &TrendConfiguration, axis: Axis) -> (usize, Option<&str>) {
+    let secondary_axis = trend
+        .secondary_axis
+        .as_ref()
+        .filter(|_| axis == Axis::Secondary);
+    let precision = secondary_axis
+        .and_then(|secondary_axis| secondary_axis.precision)
+        .or(trend.precision)
+        .unwrap_or(0);
+    let unit = match axis {
+        Axis::Primary => trend.yunit.as_deref(),
+        Axis::Secondary => {
+            secondary_axis.and_then(|secondary_axis| secondary_axis.yunit.as_deref())
+        }
+    };
+    (precision, unit)
+}
+
+/// Return the label in the legend of the comparison period of a time-series
+fn comparison_label(trend: &TrendConfiguration, series: &PlotSeries) -> String {
+    format!(
+        "{} ({})",
+        series.label,
+        trend.compare_label.as_deref().unwrap_or("previous")
+    )
+}
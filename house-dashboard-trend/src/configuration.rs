@@ -6,9 +6,20 @@
 
 //! Data structures for parsing configuration
 
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration as StdDuration;
+
+use chrono::Duration;
+
 use serde::Deserialize;
 
 use house_dashboard_common::duration::Iso8601Duration;
+use house_dashboard_common::palette::HexColor;
+use house_dashboard_common::window::TimeWindow;
+
+use house_dashboard_influxdb::{LabelTemplate, TagKeys};
+
+use crate::{Error, Transform};
 
 /// Chart configuration for infrastructure summary charts
 #[derive(Debug, Deserialize)]
@@ -34,6 +45,12 @@ pub struct TrendConfiguration {
     /// Setting to hide the legend
     pub hide_legend: Option<bool>,
 
+    /// Legend position
+    pub legend_position: Option<LegendPosition>,
+
+    /// Statistics of each series shown in the legend
+    pub legend_statistics: Option<Vec<LegendStatistic>>,
+
     /// Top padding
     pub top_padding: Option<f64>,
 
@@ -49,14 +66,29 @@ pub struct TrendConfiguration {
     /// Minimal Y range
     pub min_y_range: Option<f64>,
 
+    /// Lower bound of the Y range
+    pub y_min: Option<f64>,
+
+    /// Upper bound of the Y range
+    pub y_max: Option<f64>,
+
+    /// Lower bound of the Y range, unless values are lower
+    pub soft_y_min: Option<f64>,
+
+    /// Upper bound of the Y range, unless values are higher
+    pub soft_y_max: Option<f64>,
+
+    /// Setting to use a logarithmic Y axis
+    pub log_scale: Option<bool>,
+
     /// InfluxDB database
-    pub database: String,
+    pub database: Option<String>,
 
     /// InfluxDB measurement
-    pub measurement: String,
+    pub measurement: Option<String>,
 
     /// InfluxDB field
-    pub field: String,
+    pub field: Option<String>,
 
     /// InfluxDB field scale
     pub scale: Option<f64>,
@@ -64,15 +96,664 @@ pub struct TrendConfiguration {
     /// InfluxDB aggregator
     pub aggregator: Option<String>,
 
-    /// InfluxDB tag name
-    pub tag: String,
+    /// InfluxDB tag names
+    pub tag: Option<TagKeys>,
+
+    /// Template for series labels
+    pub label: Option<LabelTemplate>,
+
+    /// Drawing style
+    pub style: Option<SeriesStyle>,
+
+    /// Baseline for areas and bars
+    pub baseline: Option<f64>,
+
+    /// Setting to draw a band between minimal and maximal values
+    pub envelope: Option<bool>,
+
+    /// Transformations of values
+    pub transforms: Option<Vec<Transform>>,
 
     /// Time of data from now
-    pub how_long_ago: Iso8601Duration,
+    pub how_long_ago: Option<Iso8601Duration>,
+
+    /// Calendar-aligned time window, instead of time from now
+    pub time_window: Option<TimeWindow>,
+
+    /// Time of forecast data from now
+    pub how_far_ahead: Option<Iso8601Duration>,
 
     /// Data frequency
     pub how_often: Option<Iso8601Duration>,
 
+    /// Time shift of the comparison period
+    pub compare_with: Option<Iso8601Duration>,
+
+    /// Legend label of the comparison period
+    pub compare_label: Option<String>,
+
+    /// Longest interval between connected points, as a multiple of `how_often`
+    pub max_gap: Option<f64>,
+
+    /// Setting to shade intervals longer than `max_gap`
+    pub shade_gaps: Option<bool>,
+
     /// InfluxDB tag values
     pub tag_values: Option<Vec<String>>,
+
+    /// Secondary Y axis
+    pub secondary_axis: Option<SecondaryAxisConfiguration>,
+
+    /// Additional series
+    pub series: Option<Vec<SeriesConfiguration>>,
+
+    /// Horizontal reference lines
+    pub thresholds: Option<Vec<ThresholdConfiguration>>,
+
+    /// Shaded target ranges
+    pub target_ranges: Option<Vec<TargetRangeConfiguration>>,
+
+    /// Event markers
+    pub annotations: Option<Vec<AnnotationConfiguration>>,
+
+    /// Setting to draw each series in its own small chart
+    pub small_multiples: Option<SmallMultiplesConfiguration>,
+}
+
+impl TrendConfiguration {
+    /// Return all series in the chart
+    ///
+    /// Fields set at the top level of the chart and on the secondary axis
+    /// define implicit series, which come before the series in the list.
+    ///
+    /// # Errors
+    ///
+    /// Return an error when a series lacks a database or a measurement, when
+    /// a series with a fixed label is not listed in the tag values, or when
+    /// the chart has no series at all.
+    pub fn all_series(&self) -> Result<Vec<SeriesConfiguration>, Error> {
+        let mut all_series = Vec::new();
+
+        if let Some(ref field) = self.field {
+            all_series.push(SeriesConfiguration {
+                scale: self.scale,
+                aggregator: self.aggregator.clone(),
+                label: self.label.clone(),
+                style: self.style,
+                baseline: self.baseline,
+                envelope: self.envelope,
+                transforms: self.transforms.clone(),
+                ..self.implicit_series(field, Axis::Primary)?
+            });
+        }
+
+        if let Some(ref secondary_axis) = self.secondary_axis {
+            if let Some(ref field) = secondary_axis.field {
+                all_series.push(SeriesConfiguration {
+                    scale: secondary_axis.scale,
+                    aggregator: secondary_axis.aggregator.clone(),
+                    label: secondary_axis.label.clone().or_else(|| self.label.clone()),
+                    style: secondary_axis.style,
+                    baseline: secondary_axis.baseline,
+                    envelope: secondary_axis.envelope,
+                    transforms: secondary_axis.transforms.clone(),
+                    ..self.implicit_series(field, Axis::Secondary)?
+                });
+            }
+        }
+
+        for series in self.series.iter().flatten() {
+            let mut series = series.clone();
+            if series.database.is_none() {
+                series.database = Some(
+                    self.database
+                        .clone()
+                        .ok_or(Error::MissingSetting("database"))?,
+                );
+            }
+            all_series.push(series);
+        }
+
+        if let Some(ref tag_values) = self.tag_values {
+            for series in &all_series {
+                let label = series.label();
+                if !label.tag_names().is_empty() {
+                    continue;
+                }
+                if let Ok(name) = label.render(&HashMap::new()) {
+                    if !tag_values.contains(&name) {
+                        return Err(Error::UnlistedSeries(name));
+                    }
+                }
+            }
+        }
+
+        if all_series.is_empty() {
+            return Err(Error::NoSeries);
+        }
+
+        Ok(all_series)
+    }
+
+    /// Return the values of thresholds, target ranges and soft bounds on an
+    /// axis
+    ///
+    /// These values must be included in the axis range.
+    /// Soft bounds only apply to the primary axis.
+    #[must_use]
+    pub fn reference_values(&self, axis: Axis) -> Vec<f64> {
+        let thresholds = self
+            .thresholds
+            .iter()
+            .flatten()
+            .filter(|threshold| threshold.axis.unwrap_or_default() == axis)
+            .map(|threshold| threshold.value);
+        let target_ranges = self
+            .target_ranges
+            .iter()
+            .flatten()
+            .filter(|target_range| target_range.axis.unwrap_or_default() == axis)
+            .flat_map(|target_range| [target_range.from, target_range.to]);
+        let soft_bounds = [self.soft_y_min, self.soft_y_max]
+            .into_iter()
+            .flatten()
+            .filter(|_| axis == Axis::Primary);
+        thresholds.chain(target_ranges).chain(soft_bounds).collect()
+    }
+
+    /// Return the fixed bounds of the primary axis
+    ///
+    /// # Errors
+    ///
+    /// Return an error when a bound is not positive on a logarithmic axis.
+    pub fn fixed_y_range(&self) -> Result<(Option<f64>, Option<f64>), Error> {
+        if self.log_scale.unwrap_or(false) {
+            if let Some(&bound) = [self.y_min, self.y_max]
+                .iter()
+                .flatten()
+                .find(|&&bound| bound <= 0.0)
+            {
+                return Err(Error::NonPositiveLogBound(bound));
+            }
+        }
+        Ok((self.y_min, self.y_max))
+    }
+
+    /// Return the shift of the comparison period, if any
+    ///
+    /// The shift is a whole number of seconds, and it is used both for
+    /// querying the previous period and for aligning it with the current one.
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the shift is shorter than one second.
+    pub fn comparison_shift(&self) -> Result<Option<Duration>, Error> {
+        self.compare_with
+            .as_ref()
+            .map(|compare_with| {
+                u64::try_from(compare_with.duration.whole_seconds())
+                    .ok()
+                    .filter(|&seconds| seconds > 0)
+                    .and_then(|seconds| Duration::from_std(StdDuration::from_secs(seconds)).ok())
+                    .ok_or(Error::NonPositiveComparisonShift)
+            })
+            .transpose()
+    }
+
+    /// Create a series from top-level settings
+    fn implicit_series(&self, field: &str, axis: Axis) -> Result<SeriesConfiguration, Error> {
+        Ok(SeriesConfiguration {
+            database: Some(
+                self.database
+                    .clone()
+                    .ok_or(Error::MissingSetting("database"))?,
+            ),
+            measurement: self
+                .measurement
+                .clone()
+                .ok_or(Error::MissingSetting("measurement"))?,
+            field: field.to_owned(),
+            scale: None,
+            aggregator: None,
+            tag: self.tag.clone(),
+            tag_filter: None,
+            label: None,
+            axis: Some(axis),
+            style: None,
+            baseline: None,
+            envelope: None,
+            forecast: None,
+            transforms: None,
+        })
+    }
+}
+
+/// Configuration for a series in a trend chart
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeriesConfiguration {
+    /// InfluxDB database, or the chart database if missing
+    pub database: Option<String>,
+
+    /// InfluxDB measurement
+    pub measurement: String,
+
+    /// InfluxDB field
+    pub field: String,
+
+    /// InfluxDB field scale
+    pub scale: Option<f64>,
+
+    /// InfluxDB aggregator
+    pub aggregator: Option<String>,
+
+    /// InfluxDB tag names
+    pub tag: Option<TagKeys>,
+
+    /// InfluxDB tag values to filter by
+    pub tag_filter: Option<BTreeMap<String, String>>,
+
+    /// Display name, or template for series labels
+    pub label: Option<LabelTemplate>,
+
+    /// Y axis
+    pub axis: Option<Axis>,
+
+    /// Drawing style
+    pub style: Option<SeriesStyle>,
+
+    /// Baseline for areas and bars
+    pub baseline: Option<f64>,
+
+    /// Setting to draw a band between minimal and maximal values
+    pub envelope: Option<bool>,
+
+    /// Setting to fetch future data up to `how_far_ahead` and draw it dashed
+    pub forecast: Option<bool>,
+
+    /// Transformations of values
+    pub transforms: Option<Vec<Transform>>,
+}
+
+impl SeriesConfiguration {
+    /// Return the label template for this series
+    ///
+    /// Series without a label are named after their tag values, or after
+    /// their field if they are not grouped by tags.
+    #[must_use]
+    pub fn label(&self) -> LabelTemplate {
+        match (self.label.as_ref(), self.tag.as_ref()) {
+            (Some(label), _) => label.clone(),
+            (None, Some(tag)) => tag.default_label(),
+            (None, None) => LabelTemplate::new(self.field.as_str()),
+        }
+    }
+}
+
+/// Configuration for a horizontal reference line
+#[derive(Debug, Deserialize)]
+pub struct ThresholdConfiguration {
+    /// Value
+    pub value: f64,
+
+    /// Label
+    pub label: Option<String>,
+
+    /// Color
+    pub color: Option<HexColor>,
+
+    /// Y axis
+    pub axis: Option<Axis>,
+}
+
+/// Configuration for a shaded target range
+#[derive(Debug, Deserialize)]
+pub struct TargetRangeConfiguration {
+    /// Lower bound
+    pub from: f64,
+
+    /// Upper bound
+    pub to: f64,
+
+    /// Label
+    pub label: Option<String>,
+
+    /// Color
+    pub color: Option<HexColor>,
+
+    /// Y axis
+    pub axis: Option<Axis>,
+
+    /// Setting to color lines outside the range with the status colormap
+    pub status_colors: Option<bool>,
+
+    /// Deviation from the range drawn with the last status color
+    ///
+    /// By default, this is the width of the range.
+    pub max_deviation: Option<f64>,
+}
+
+impl TargetRangeConfiguration {
+    /// Return the distance of a value from the range
+    #[must_use]
+    pub fn deviation(&self, value: f64) -> f64 {
+        (self.from - value).max(value - self.to).max(0.0)
+    }
+}
+
+/// Configuration for event markers
+///
+/// Each event is a point in a measurement, labelled by the value of a string
+/// field or by a fixed label.
+#[derive(Debug, Deserialize)]
+pub struct AnnotationConfiguration {
+    /// InfluxDB database, or the chart database if missing
+    pub database: Option<String>,
+
+    /// InfluxDB measurement
+    pub measurement: String,
+
+    /// InfluxDB field
+    pub field: String,
+
+    /// Additional query condition, such as `state = 'on'`
+    pub condition: Option<String>,
+
+    /// Fixed label, instead of the field value
+    pub label: Option<String>,
+
+    /// Color
+    pub color: Option<HexColor>,
+}
+
+/// A Y axis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    /// Primary axis, on the left
+    #[default]
+    Primary,
+
+    /// Secondary axis, on the right
+    Secondary,
+}
+
+/// Drawing style of a series
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesStyle {
+    /// Line through all points
+    #[default]
+    Line,
+
+    /// Line holding each value until the next point
+    Step,
+
+    /// Area filled between the line and the baseline
+    Area,
+
+    /// Area stacked on top of previous stacked areas
+    StackedArea,
+
+    /// Bars spanning each period
+    Bars,
+}
+
+/// Position of the legend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegendPosition {
+    /// Upper left corner of the plot
+    #[default]
+    UpperLeft,
+
+    /// Upper right corner of the plot
+    UpperRight,
+
+    /// Lower left corner of the plot
+    LowerLeft,
+
+    /// Lower right corner of the plot
+    LowerRight,
+
+    /// Corner of the plot with the fewest data points
+    Auto,
+
+    /// Right of the plot, outside of it
+    Outside,
+}
+
+/// A statistic of a series shown in the legend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegendStatistic {
+    /// Minimal value
+    Min,
+
+    /// Mean value
+    Mean,
+
+    /// Maximal value
+    Max,
+
+    /// Last value
+    Last,
+}
+
+/// Configuration for small multiples
+///
+/// Each series is drawn in its own small chart, and charts are arranged in a
+/// grid sharing the X axis.
+#[derive(Debug, Default, Deserialize)]
+pub struct SmallMultiplesConfiguration {
+    /// Number of columns, or enough for a square grid if missing
+    pub columns: Option<usize>,
+
+    /// Setting to use the same Y range in all charts on the same axis
+    pub share_y_range: Option<bool>,
+}
+
+impl SmallMultiplesConfiguration {
+    /// Return the number of rows and columns of the grid for some charts
+    ///
+    /// The grid has at least one row and one column.
+    #[must_use]
+    pub fn grid(&self, count: usize) -> (usize, usize) {
+        let columns = self
+            .columns
+            .unwrap_or_else(|| {
+                (1..=count)
+                    .find(|columns| columns * columns >= count)
+                    .unwrap_or(1)
+            })
+            .max(1);
+        let rows = count.div_ceil(columns).max(1);
+        (rows, columns)
+    }
+}
+
+/// Configuration for a secondary Y axis
+#[derive(Debug, Deserialize)]
+pub struct SecondaryAxisConfiguration {
+    /// Y label
+    pub ylabel: Option<String>,
+
+    /// Y unit
+    pub yunit: Option<String>,
+
+    /// Precision
+    pub precision: Option<usize>,
+
+    /// Y range
+    pub range: Option<(f64, f64)>,
+
+    /// InfluxDB field
+    pub field: Option<String>,
+
+    /// InfluxDB field scale
+    pub scale: Option<f64>,
+
+    /// InfluxDB aggregator
+    pub aggregator: Option<String>,
+
+    /// Template for series labels
+    pub label: Option<LabelTemplate>,
+
+    /// Drawing style
+    pub style: Option<SeriesStyle>,
+
+    /// Baseline for areas and bars
+    pub baseline: Option<f64>,
+
+    /// Setting to draw a band between minimal and maximal values
+    pub envelope: Option<bool>,
+
+    /// Transformations of values
+    pub transforms: Option<Vec<Transform>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Common settings for all test charts
+    const HEADER: &str = r#"
+        title = "Temperature"
+        xlabel_format = "%H:%M"
+        how_long_ago = "P1D"
+        database = "house"
+    "#;
+
+    #[test]
+    fn implicit_and_explicit_series() {
+        let text = format!(
+            r#"{HEADER}
+            measurement = "indoor_environment"
+            field = "temperature"
+            tag = "room"
+
+            [[series]]
+            database = "weather"
+            measurement = "outdoor_environment"
+            field = "temperature"
+            label = "Outdoor"
+
+            [[series]]
+            measurement = "indoor_environment"
+            field = "humidity"
+            tag_filter = {{ room = "Kitchen" }}
+            axis = "secondary"
+            "#
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let all_series = trend
+            .and_then(|trend| trend.all_series().ok())
+            .unwrap_or_default();
+
+        assert_eq!(all_series.len(), 3);
+        assert!(all_series.iter().all(|series| series.database.is_some()));
+        assert_eq!(all_series[0].measurement, "indoor_environment");
+        assert_eq!(all_series[0].label(), LabelTemplate::new("{room}"));
+        assert_eq!(all_series[1].database.as_deref(), Some("weather"));
+        assert_eq!(all_series[1].label(), LabelTemplate::new("Outdoor"));
+        assert_eq!(all_series[2].database.as_deref(), Some("house"));
+        assert_eq!(all_series[2].axis, Some(Axis::Secondary));
+        assert_eq!(all_series[2].label(), LabelTemplate::new("humidity"));
+    }
+
+    #[test]
+    fn small_multiples_grid() {
+        let square = SmallMultiplesConfiguration::default();
+        assert_eq!(square.grid(0), (1, 1));
+        assert_eq!(square.grid(1), (1, 1));
+        assert_eq!(square.grid(5), (2, 3));
+        assert_eq!(square.grid(8), (3, 3));
+        assert_eq!(square.grid(9), (3, 3));
+
+        let columns = SmallMultiplesConfiguration {
+            columns: Some(2),
+            share_y_range: None,
+        };
+        assert_eq!(columns.grid(8), (4, 2));
+        assert_eq!(columns.grid(7), (4, 2));
+    }
+
+    #[test]
+    fn non_positive_log_bound() {
+        let text = format!(
+            "{HEADER}
+            log_scale = true
+            y_min = 0
+            y_max = 1000
+            "
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let range = trend.as_ref().map(TrendConfiguration::fixed_y_range);
+        assert!(matches!(range, Some(Err(Error::NonPositiveLogBound(bound))) if bound == 0.0));
+
+        let text = format!(
+            "{HEADER}
+            y_min = 0
+            "
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let range = trend.map(|trend| trend.fixed_y_range().ok());
+        assert_eq!(range, Some(Some((Some(0.0), None))));
+    }
+
+    #[test]
+    fn tag_values_with_extra_series() {
+        let text = format!(
+            r#"{HEADER}
+            measurement = "indoor_environment"
+            field = "temperature"
+            tag = "room"
+            tag_values = ["Kitchen", "Bedroom"]
+
+            [[series]]
+            database = "weather"
+            measurement = "outdoor_environment"
+            field = "temperature"
+            label = "Outdoor"
+            "#
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let result = trend.map(|trend| trend.all_series());
+        assert!(matches!(result, Some(Err(Error::UnlistedSeries(name))) if name == "Outdoor"));
+
+        let text = text.replace(r#"["Kitchen", "Bedroom"]"#, r#"["Kitchen", "Outdoor"]"#);
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let all_series = trend.and_then(|trend| trend.all_series().ok());
+        assert_eq!(all_series.map(|all_series| all_series.len()), Some(2));
+    }
+
+    #[test]
+    fn comparison_shift() {
+        let text = format!(
+            r#"{HEADER}
+            compare_with = "P7D"
+            "#
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let shift = trend.map(|trend| trend.comparison_shift().ok());
+        assert_eq!(shift, Some(Some(Some(Duration::days(7)))));
+
+        let text = format!(
+            r#"{HEADER}
+            compare_with = "PT0S"
+            "#
+        );
+        let trend: Option<TrendConfiguration> = toml::from_str(&text).ok();
+        let shift = trend.map(|trend| trend.comparison_shift());
+        assert!(matches!(
+            shift,
+            Some(Err(Error::NonPositiveComparisonShift))
+        ));
+    }
+
+    #[test]
+    fn no_series() {
+        let trend: Option<TrendConfiguration> = toml::from_str(HEADER).ok();
+        let result = trend.map(|trend| trend.all_series());
+        assert!(matches!(result, Some(Err(Error::NoSeries))));
+    }
 }
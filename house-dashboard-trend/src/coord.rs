@@ -0,0 +1,96 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for linear and logarithmic value axes
+
+use std::ops::Range;
+
+use plotters::coord::{
+    combinators::{IntoLogRange, LogCoord},
+    ranged1d::{DefaultFormatting, KeyPointHint, Ranged},
+    types::RangedCoordf64,
+};
+
+/// A value axis, either linear or logarithmic
+///
+/// The choice between the two scales is made at runtime, so that charts have
+/// the same type regardless of their scale.
+pub enum ValueCoord {
+    /// Linear axis
+    Linear(RangedCoordf64),
+
+    /// Logarithmic axis
+    Logarithmic(LogCoord<f64>),
+}
+
+impl ValueCoord {
+    /// Create a value axis over a range
+    ///
+    /// Logarithmic axes require a positive range.
+    pub fn new(range: Range<f64>, logarithmic: bool) -> Self {
+        if logarithmic {
+            Self::Logarithmic(range.log_scale().into())
+        } else {
+            Self::Linear(range.into())
+        }
+    }
+}
+
+impl Ranged for ValueCoord {
+    type FormatOption = DefaultFormatting;
+    type ValueType = f64;
+
+    /// Map a value to a pixel
+    ///
+    /// Non-positive values on logarithmic axes are mapped to the bottom of the
+    /// axis.
+    fn map(&self, value: &f64, limit: (i32, i32)) -> i32 {
+        match *self {
+            Self::Linear(ref coord) => coord.map(value, limit),
+            Self::Logarithmic(ref coord) => {
+                let start = coord.range().start;
+                let value = if *value > 0.0 { *value } else { start };
+                coord.map(&value, limit)
+            }
+        }
+    }
+
+    fn key_points<Hint: KeyPointHint>(&self, hint: Hint) -> Vec<f64> {
+        match *self {
+            Self::Linear(ref coord) => coord.key_points(hint),
+            Self::Logarithmic(ref coord) => coord.key_points(hint),
+        }
+    }
+
+    fn range(&self) -> Range<f64> {
+        match *self {
+            Self::Linear(ref coord) => coord.range(),
+            Self::Logarithmic(ref coord) => coord.range(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logarithmic_map() {
+        let coord = ValueCoord::new(1.0..1000.0, true);
+        assert_eq!(coord.map(&1.0, (0, 300)), 0);
+        assert_eq!(coord.map(&10.0, (0, 300)), 100);
+        assert_eq!(coord.map(&100.0, (0, 300)), 200);
+        assert_eq!(coord.map(&0.0, (0, 300)), 0);
+        assert_eq!(coord.key_points(3), vec![1.0, 10.0, 100.0, 1000.0]);
+    }
+
+    #[test]
+    fn linear_map() {
+        let coord = ValueCoord::new(0.0..30.0, false);
+        assert_eq!(coord.map(&10.0, (0, 300)), 100);
+        assert_eq!(coord.range(), 0.0..30.0);
+    }
+}
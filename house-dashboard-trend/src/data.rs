@@ -0,0 +1,82 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types for trend chart data
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use plotters::style::RGBColor;
+
+use house_dashboard_common::types::TimeSeries;
+
+use crate::configuration::{Axis, SeriesStyle};
+
+/// Minimal and maximal values of a time-series
+type Envelope = Vec<(DateTime<Utc>, f64, f64)>;
+
+/// Data for a trend chart
+#[derive(Debug, Default, Clone)]
+pub struct TrendData {
+    /// Time-series on the primary axis
+    pub time_seriess: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+
+    /// Time-series on the secondary axis
+    pub secondary_time_seriess: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+
+    /// Minimal and maximal values around each time-series, by axis
+    pub envelopes: HashMap<(Axis, String), Envelope>,
+
+    /// Time-series over the comparison period, aligned to the current period,
+    /// by axis
+    pub comparisons: HashMap<(Axis, String), TimeSeries>,
+
+    /// Drawing options for each time-series, by axis
+    ///
+    /// Time-series without options are drawn as lines.
+    pub options: HashMap<(Axis, String), SeriesOptions>,
+
+    /// Event markers
+    pub annotations: Vec<Annotation>,
+}
+
+impl TrendData {
+    /// Return the drawing options for a time-series on an axis
+    #[must_use]
+    pub fn options(&self, axis: Axis, name: &str) -> SeriesOptions {
+        self.options
+            .get(&(axis, name.to_owned()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Drawing options for a time-series
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SeriesOptions {
+    /// Drawing style
+    pub style: SeriesStyle,
+
+    /// Baseline for areas and bars
+    pub baseline: f64,
+
+    /// Whether the time-series is a forecast
+    pub forecast: bool,
+}
+
+/// An event marker
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Instant of the event
+    pub instant: DateTime<Utc>,
+
+    /// Label
+    pub text: String,
+
+    /// Color, or the foreground color if missing
+    pub color: Option<RGBColor>,
+}
@@ -14,6 +14,8 @@
 
 use plotters::drawing::DrawingAreaErrorKind;
 
+use house_dashboard_common::error::ColormapCreationError;
+
 /// An error occurred generating a chart
 #[derive(ThisError, Debug, Diagnostic)]
 pub enum Error {
@@ -33,6 +35,40 @@ pub enum Error {
     #[error("font error")]
     Font,
 
+    /// A setting is required by a series but missing
+    #[error("missing setting {0}")]
+    #[diagnostic(help("set it at the top level of the chart or in each series"))]
+    MissingSetting(&'static str),
+
+    /// Chart has no series
+    #[error("no series defined")]
+    #[diagnostic(help("set a top-level field or add a [[series]] section"))]
+    NoSeries,
+
+    /// A series with a fixed label is not listed in the tag values
+    #[error("series \"{0}\" is not listed in tag_values")]
+    #[diagnostic(help("add the label of the series to tag_values, or remove tag_values"))]
+    UnlistedSeries(String),
+
+    /// The comparison shift is not a positive number of seconds
+    #[error("comparison shift is not a positive duration")]
+    #[diagnostic(help("set compare_with to a duration of at least one second, such as \"P7D\""))]
+    NonPositiveComparisonShift,
+
+    /// A fixed bound is not positive on a logarithmic axis
+    #[error("fixed bound {0} is not positive on a logarithmic axis")]
+    #[diagnostic(help("set y_min and y_max to positive values, or remove them"))]
+    NonPositiveLogBound(f64),
+
+    /// Expression is not valid
+    #[error("invalid expression: {0}")]
+    #[diagnostic(help("use numbers, the variable x, the operators + - * / and parentheses"))]
+    InvalidExpression(String),
+
+    /// Colormap creation failed
+    #[error("Colormap creation failed")]
+    ColormapCreation(#[from] ColormapCreationError),
+
     /// Integer conversion failed
     #[error(transparent)]
     TryFromInt(#[from] TryFromIntError),
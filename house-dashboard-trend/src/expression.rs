@@ -0,0 +1,260 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for evaluating arithmetic expressions
+
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer};
+
+use crate::Error;
+
+/// An arithmetic expression of a value `x`
+///
+/// Expressions support numbers, the variable `x`, the operators `+`, `-`,
+/// `*` and `/`, and parentheses, such as `(x - 32) * 5 / 9`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    /// Root of the syntax tree
+    root: Node,
+}
+
+impl Expression {
+    /// Evaluate the expression for a value
+    #[must_use]
+    pub fn evaluate(&self, x: f64) -> f64 {
+        self.root.evaluate(x)
+    }
+}
+
+impl FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            source: string,
+            chars: string.char_indices().peekable(),
+        };
+        let root = parser.parse_sum()?;
+        parser.skip_whitespace();
+        if let Some(&(position, character)) = parser.chars.peek() {
+            return Err(parser.error(position, &format!("unexpected '{character}'")));
+        }
+        Ok(Self { root })
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(D::Error::custom)
+    }
+}
+
+/// A node in the syntax tree of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// The variable `x`
+    Variable,
+
+    /// A constant number
+    Constant(f64),
+
+    /// Negation of a node
+    Negate(Box<Node>),
+
+    /// Binary operation between two nodes
+    Binary(Operator, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    /// Evaluate the node for a value
+    fn evaluate(&self, x: f64) -> f64 {
+        match *self {
+            Self::Variable => x,
+            Self::Constant(value) => value,
+            Self::Negate(ref node) => -node.evaluate(x),
+            Self::Binary(operator, ref left, ref right) => {
+                let (left, right) = (left.evaluate(x), right.evaluate(x));
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide => left / right,
+                }
+            }
+        }
+    }
+}
+
+/// A binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    /// Addition
+    Add,
+
+    /// Subtraction
+    Subtract,
+
+    /// Multiplication
+    Multiply,
+
+    /// Division
+    Divide,
+}
+
+/// A recursive descent parser for expressions
+struct Parser<'a> {
+    /// Expression source
+    source: &'a str,
+
+    /// Remaining characters and their positions
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// Parse a sum or difference of products
+    fn parse_sum(&mut self) -> Result<Node, Error> {
+        let mut node = self.parse_product()?;
+        loop {
+            let operator = match self.peek() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Subtract,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            let right = self.parse_product()?;
+            node = Node::Binary(operator, Box::new(node), Box::new(right));
+        }
+    }
+
+    /// Parse a product or quotient of factors
+    fn parse_product(&mut self) -> Result<Node, Error> {
+        let mut node = self.parse_factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                _ => return Ok(node),
+            };
+            self.chars.next();
+            let right = self.parse_factor()?;
+            node = Node::Binary(operator, Box::new(node), Box::new(right));
+        }
+    }
+
+    /// Parse a number, the variable, a negated factor or a parenthesized sum
+    fn parse_factor(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+        let Some(&(position, character)) = self.chars.peek() else {
+            return Err(self.error(self.source.len(), "unexpected end of expression"));
+        };
+
+        match character {
+            'x' => {
+                self.chars.next();
+                Ok(Node::Variable)
+            }
+            '-' => {
+                self.chars.next();
+                Ok(Node::Negate(Box::new(self.parse_factor()?)))
+            }
+            '(' => {
+                self.chars.next();
+                let node = self.parse_sum()?;
+                if self.peek() == Some(')') {
+                    self.chars.next();
+                    Ok(node)
+                } else {
+                    Err(self.error(position, "unclosed parenthesis"))
+                }
+            }
+            '0'..='9' | '.' => self.parse_number(position),
+            _ => Err(self.error(position, &format!("unexpected '{character}'"))),
+        }
+    }
+
+    /// Parse a number starting at a position
+    fn parse_number(&mut self, start: usize) -> Result<Node, Error> {
+        let mut number = String::new();
+        while let Some((_, character)) = self
+            .chars
+            .next_if(|&(_, character)| character.is_ascii_digit() || character == '.')
+        {
+            number.push(character);
+        }
+
+        number
+            .parse()
+            .map(Node::Constant)
+            .map_err(|_| self.error(start, &format!("invalid number '{number}'")))
+    }
+
+    /// Return the next character after whitespace, without consuming it
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|&(_, character)| character)
+    }
+
+    /// Consume whitespace
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|&(_, character)| character.is_whitespace())
+            .is_some()
+        {}
+    }
+
+    /// Create an error at a position
+    fn error(&self, position: usize, message: &str) -> Error {
+        Error::InvalidExpression(format!(
+            "{message} at position {position} in '{}'",
+            self.source
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse and evaluate an expression
+    fn evaluate(expression: &str, x: f64) -> Option<f64> {
+        expression
+            .parse::<Expression>()
+            .ok()
+            .map(|expression| expression.evaluate(x))
+    }
+
+    #[test]
+    fn evaluate_precedence() {
+        assert_eq!(evaluate("x * 9 / 5 + 32", 100.0), Some(212.0));
+        assert_eq!(evaluate("2 + 3 * x", 4.0), Some(14.0));
+        assert_eq!(evaluate("10 - 4 - x", 1.0), Some(5.0));
+    }
+
+    #[test]
+    fn evaluate_parentheses_and_negation() {
+        assert_eq!(evaluate("(x - 32) * 5 / 9", 212.0), Some(100.0));
+        assert_eq!(evaluate("-(x + 1)", 2.0), Some(-3.0));
+        assert_eq!(evaluate("x / 1000", 2500.0), Some(2.5));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!("".parse::<Expression>().is_err());
+        assert!("x +".parse::<Expression>().is_err());
+        assert!("(x * 2".parse::<Expression>().is_err());
+        assert!("y * 2".parse::<Expression>().is_err());
+        assert!("1.2.3".parse::<Expression>().is_err());
+        assert!("x 2".parse::<Expression>().is_err());
+    }
+}
@@ -6,64 +6,295 @@
 
 //! Data types and functions for fetching data for trend charts
 
-use std::collections::HashMap;
-
 use tracing::debug;
 
 use miette::{IntoDiagnostic, Report, WrapErr};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 use house_dashboard_common::duration::duration_to_query;
+use house_dashboard_common::window::{local_tz_clause, query_bounds};
 
 use house_dashboard_influxdb::Error as InfluxDBError;
-use house_dashboard_influxdb::InfluxDBClient;
+use house_dashboard_influxdb::{Frame, InfluxDBClient};
 
-use crate::configuration::TrendConfiguration;
+use crate::configuration::{
+    AnnotationConfiguration, Axis, SeriesConfiguration, TrendConfiguration,
+};
+use crate::data::{Annotation, SeriesOptions, TrendData};
+use crate::transform::{apply_transforms, apply_transforms_to_envelope};
+use crate::Error;
 
 /// Fetch data for trend
 ///
+/// Each series is fetched with a separate query, and its time-series are
+/// assigned to the primary or the secondary axis together with their drawing
+/// options.
+/// Time-series on the same axis must have distinct labels.
+/// When the chart is compared with a previous period, each series except
+/// forecasts is fetched again over the shifted period, and its time-series are
+/// shifted forward to align with the current period.
+///
 /// # Errors
 ///
-/// Return and error when data could not be fetched
+/// Return and error when data could not be fetched, when two time-series
+/// on the same axis have the same label, or when the comparison shift is not
+/// positive
 pub async fn fetch_data(
     influxdb_client: &InfluxDBClient,
     trend_configuration: &TrendConfiguration,
-) -> Result<HashMap<String, Vec<(DateTime<Utc>, f64)>>, Report> {
+) -> Result<TrendData, Report> {
+    let mut data = TrendData::default();
+    let comparison_shift = trend_configuration.comparison_shift()?;
+
+    for series in trend_configuration.all_series()? {
+        let time_seriess = fetch_series(influxdb_client, trend_configuration, &series, None)
+            .await
+            .wrap_err_with(|| format!("cannot fetch series for field {}", series.field))?;
+
+        let options = SeriesOptions {
+            style: series.style.unwrap_or_default(),
+            baseline: series.baseline.unwrap_or(0.0),
+            forecast: series.forecast.unwrap_or(false),
+        };
+
+        let axis = series.axis.unwrap_or_default();
+        let axis_time_seriess = match axis {
+            Axis::Primary => &mut data.time_seriess,
+            Axis::Secondary => &mut data.secondary_time_seriess,
+        };
+        for time_series in time_seriess {
+            let name = time_series.name;
+            if axis_time_seriess.contains_key(&name) {
+                return Err(InfluxDBError::DuplicateLabel(name)).wrap_err("cannot name series");
+            }
+            data.options.insert((axis, name.clone()), options);
+            if let Some(envelope) = time_series.envelope {
+                data.envelopes.insert((axis, name.clone()), envelope);
+            }
+            axis_time_seriess.insert(name, time_series.values);
+        }
+
+        if let Some(shift) = comparison_shift {
+            if series.forecast.unwrap_or(false) {
+                debug!("Skipping comparison of forecast series {}", series.field);
+                continue;
+            }
+
+            let comparison_series = SeriesConfiguration {
+                envelope: None,
+                ..series.clone()
+            };
+            let time_seriess = fetch_series(
+                influxdb_client,
+                trend_configuration,
+                &comparison_series,
+                Some(shift),
+            )
+            .await
+            .wrap_err_with(|| format!("cannot fetch comparison for field {}", series.field))?;
+
+            for time_series in time_seriess {
+                let values = time_series
+                    .values
+                    .into_iter()
+                    .map(|(instant, value)| (instant + shift, value))
+                    .collect();
+                data.comparisons.insert((axis, time_series.name), values);
+            }
+        }
+    }
+
+    for annotation in trend_configuration.annotations.iter().flatten() {
+        let annotations = fetch_annotations(influxdb_client, trend_configuration, annotation)
+            .await
+            .wrap_err_with(|| format!("cannot fetch annotations for field {}", annotation.field))?;
+        data.annotations.extend(annotations);
+    }
+
+    Ok(data)
+}
+
+/// A time-series fetched from InfluxDB
+struct FetchedTimeSeries {
+    /// Name
+    name: String,
+
+    /// Aggregated values
+    values: Vec<(DateTime<Utc>, f64)>,
+
+    /// Minimal and maximal values, if requested
+    envelope: Option<Vec<(DateTime<Utc>, f64, f64)>>,
+}
+
+/// Fetch time-series of a series
+///
+/// Values are aggregated over intervals aligned to the local timezone, so
+/// daily and weekly intervals start at local midnight.
+/// Values are transformed after aggregation, and envelopes are discarded
+/// unless all transformations are pointwise.
+/// When a shift is given, the query covers the same period shifted back in
+/// time, and the instants are left unchanged.
+async fn fetch_series(
+    influxdb_client: &InfluxDBClient,
+    trend_configuration: &TrendConfiguration,
+    series: &SeriesConfiguration,
+    shift: Option<Duration>,
+) -> Result<Vec<FetchedTimeSeries>, Report> {
+    let filter = series
+        .tag_filter
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!(r#" AND "{key}" = '{}'"#, value.replace('\'', r"\'")))
+        .collect::<Vec<String>>()
+        .concat();
+    let tags = series
+        .tag
+        .as_ref()
+        .map(|tag| format!(",{}", tag.to_group_by()))
+        .unwrap_or_default();
+
+    let scale = series.scale.unwrap_or(1.0);
+    let field = &series.field;
+    let envelope = if series.envelope.unwrap_or(false) {
+        format!(", {scale} * min({field}) AS min, {scale} * max({field}) AS max")
+    } else {
+        String::new()
+    };
+
+    let (since, until) = query_period(trend_configuration, series.forecast.unwrap_or(false))?;
+
+    let shift = shift
+        .map(|shift| format!(" - {}s", shift.num_seconds()))
+        .unwrap_or_default();
+
     let query = format!(
-        "SELECT {scale} * {aggregator}({field}) FROM {database}.autogen.{measurement}
-        WHERE time < now() AND time > now() - {how_long_ago}
-        GROUP BY time({period}),{tag} FILL(none)",
-        database = trend_configuration.database,
-        scale = trend_configuration.scale.unwrap_or(1.0),
-        aggregator = trend_configuration
-            .aggregator
-            .clone()
-            .unwrap_or_else(|| "mean".to_owned()),
-        field = trend_configuration.field,
-        measurement = trend_configuration.measurement,
-        tag = trend_configuration.tag,
+        "SELECT {scale} * {aggregator}({field}) AS value{envelope}
+        FROM {database}.autogen.{measurement}
+        WHERE time < {until}{shift} AND time > {since}{shift}{filter}
+        GROUP BY time({period}){tags} FILL(none) {tz}",
+        database = series.database.as_deref().unwrap_or_default(),
+        aggregator = series.aggregator.as_deref().unwrap_or("mean"),
+        measurement = series.measurement,
         period = trend_configuration
             .how_often
             .as_ref()
             .map_or_else(|| Ok("1h".to_owned()), |d| duration_to_query(&d.duration),)
             .into_diagnostic()?,
-        how_long_ago =
-            duration_to_query(&trend_configuration.how_long_ago.duration).into_diagnostic()?,
+        tz = local_tz_clause()?,
     );
 
     debug!("Query: {}", query);
 
-    let time_seriess = match influxdb_client
-        .fetch_tagged_dataframes(&query, &trend_configuration.tag)
-        .await
-    {
-        Ok(time_seriess) => Ok(time_seriess),
-        Err(InfluxDBError::EmptySeries) => Ok(HashMap::new()),
+    let frames = match influxdb_client.fetch_frames(&query).await {
+        Ok(frames) => Ok(frames),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => Ok(Vec::new()),
         other => other,
     }
-    .into_diagnostic()
     .wrap_err("cannot fetch time-series")?;
 
-    Ok(time_seriess)
+    let label = series.label();
+    let transforms = series.transforms.as_deref().unwrap_or_default();
+    frames
+        .iter()
+        .map(|frame| {
+            let name = label.render(frame.tags())?;
+            let values = frame.float_column("value").unwrap_or_default();
+            let values = apply_transforms(transforms, values);
+            let envelope = frame
+                .float_column("min")
+                .zip(frame.float_column("max"))
+                .map(|(minima, maxima)| {
+                    minima
+                        .into_iter()
+                        .zip(maxima)
+                        .map(|((instant, min), (_, max))| (instant, min.min(max), min.max(max)))
+                        .collect()
+                })
+                .and_then(|envelope| apply_transforms_to_envelope(transforms, envelope));
+            Ok(FetchedTimeSeries {
+                name,
+                values,
+                envelope,
+            })
+        })
+        .collect()
+}
+
+/// Fetch events of an annotation
+///
+/// Events without a label are skipped.
+async fn fetch_annotations(
+    influxdb_client: &InfluxDBClient,
+    trend_configuration: &TrendConfiguration,
+    annotation: &AnnotationConfiguration,
+) -> Result<Vec<Annotation>, Report> {
+    let (since, until) = query_period(trend_configuration, true)?;
+    let condition = annotation
+        .condition
+        .as_ref()
+        .map(|condition| format!(" AND ({condition})"))
+        .unwrap_or_default();
+    let database = annotation
+        .database
+        .as_ref()
+        .or(trend_configuration.database.as_ref())
+        .ok_or(Error::MissingSetting("database"))?;
+
+    let query = format!(
+        "SELECT {field}
+        FROM {database}.autogen.{measurement}
+        WHERE time < {until} AND time > {since}{condition}",
+        field = annotation.field,
+        measurement = annotation.measurement,
+    );
+
+    debug!("Query: {}", query);
+
+    let frames = match influxdb_client.fetch_frames(&query).await {
+        Ok(frames) => Ok(frames),
+        Err(error) if matches!(error.root_cause(), InfluxDBError::EmptySeries) => Ok(Vec::new()),
+        other => other,
+    }
+    .wrap_err("cannot fetch events")?;
+
+    let annotations = frames
+        .iter()
+        .flat_map(Frame::first_string_column)
+        .filter_map(|(instant, value)| {
+            let text = annotation.label.clone().unwrap_or(value);
+            (!text.is_empty()).then(|| Annotation {
+                instant,
+                text,
+                color: annotation.color.map(|color| color.0),
+            })
+        })
+        .collect();
+
+    Ok(annotations)
+}
+
+/// Return the start and end of the queried period
+///
+/// The period is either a calendar-aligned time window or a rolling period
+/// ending now.
+/// Future data is included up to `how_far_ahead` after its end when requested.
+fn query_period(
+    trend_configuration: &TrendConfiguration,
+    future: bool,
+) -> Result<(String, String), Report> {
+    let (since, until) = query_bounds(
+        trend_configuration.how_long_ago.as_ref(),
+        trend_configuration.time_window,
+    )?;
+    match trend_configuration.how_far_ahead {
+        Some(ref how_far_ahead) if future => Ok((
+            since,
+            format!(
+                "{until} + {}",
+                duration_to_query(&how_far_ahead.duration).into_diagnostic()?
+            ),
+        )),
+        _ => Ok((since, until)),
+    }
 }
@@ -25,14 +25,29 @@
 pub use self::chart::draw_trend;
 
 mod configuration;
-pub use self::configuration::TrendConfiguration;
+pub use self::configuration::{
+    AnnotationConfiguration, Axis, LegendPosition, LegendStatistic, SecondaryAxisConfiguration,
+    SeriesConfiguration, SeriesStyle, SmallMultiplesConfiguration, TargetRangeConfiguration,
+    ThresholdConfiguration, TrendConfiguration,
+};
+
+mod coord;
+
+mod data;
+pub use self::data::{Annotation, SeriesOptions, TrendData};
 
 mod error;
 pub use self::error::Error;
 
+mod expression;
+pub use self::expression::Expression;
+
 mod influxdb;
 use self::influxdb::fetch_data;
 
+mod transform;
+pub use self::transform::{apply_transforms, Transform};
+
 /// Fetch data and draw chart for trend
 ///
 /// # Errors
@@ -49,18 +64,22 @@ pub async fn process_trend(
     style_configuration: &StyleConfiguration,
     index: usize,
 ) -> Result<Vec<u8>, Report> {
-    let time_seriess = fetch_data(influxdb_client, trend_configuration)
+    let data = fetch_data(influxdb_client, trend_configuration)
         .await
         .wrap_err("cannot fetch data for trend")?;
 
-    let duration =
-        chrono::Duration::from_std(trend_configuration.how_long_ago.duration.unsigned_abs())
-            .into_diagnostic()?;
+    let ahead = trend_configuration
+        .how_far_ahead
+        .as_ref()
+        .map(|how_far_ahead| chrono::Duration::from_std(how_far_ahead.duration.unsigned_abs()))
+        .transpose()
+        .into_diagnostic()?
+        .unwrap_or_else(chrono::Duration::zero);
+    let (start, end) = time_range(trend_configuration)?;
     let x_range: (DateTime<Local>, DateTime<Local>) = (
-        Local::now()
-            .checked_sub_signed(duration)
+        start,
+        end.checked_add_signed(ahead)
             .ok_or(miette!("Invalid duration"))?,
-        Local::now(),
     );
 
     let area = style_configuration.resolution.0 * style_configuration.resolution.1;
@@ -70,7 +89,7 @@ pub async fn process_trend(
     draw_trend(
         trend_configuration,
         x_range,
-        &time_seriess,
+        &data,
         style_configuration,
         backend,
     )
@@ -78,3 +97,32 @@ pub async fn process_trend(
 
     Ok(buffer)
 }
+
+/// Return the start and end of the time range of a trend chart
+///
+/// The range is either a calendar-aligned time window or a rolling period
+/// ending now.
+fn time_range(
+    trend_configuration: &TrendConfiguration,
+) -> Result<(DateTime<Local>, DateTime<Local>), Report> {
+    if let Some(time_window) = trend_configuration.time_window {
+        let (start, end) = time_window.local_bounds()?;
+        let [start, end] = [start, end].map(|instant| {
+            DateTime::from_timestamp(instant.unix_timestamp(), instant.nanosecond())
+                .map(|instant| instant.with_timezone(&Local))
+        });
+        return start.zip(end).ok_or(miette!("Invalid time window"));
+    }
+
+    let how_long_ago = trend_configuration
+        .how_long_ago
+        .as_ref()
+        .ok_or(Error::MissingSetting("how_long_ago"))?;
+    let duration =
+        chrono::Duration::from_std(how_long_ago.duration.unsigned_abs()).into_diagnostic()?;
+    let now = Local::now();
+    let start = now
+        .checked_sub_signed(duration)
+        .ok_or(miette!("Invalid duration"))?;
+    Ok((start, now))
+}
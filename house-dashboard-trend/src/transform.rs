@@ -0,0 +1,295 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for transforming time-series
+
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+
+use serde::Deserialize;
+
+use chrono::{DateTime, Utc};
+
+use house_dashboard_common::duration::Iso8601Duration;
+
+use crate::expression::Expression;
+
+/// A transformation of a time-series
+///
+/// Transformations are applied to the aggregated values, in the order they
+/// are listed in the series configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transform {
+    /// Difference between each value and the previous one
+    Difference,
+
+    /// Difference between each value and the previous one per unit of time
+    Derivative {
+        /// Unit of time, or one hour if missing
+        unit: Option<Iso8601Duration>,
+    },
+
+    /// Sum of all values since the start of the range
+    CumulativeSum,
+
+    /// Average of each value and the previous ones
+    MovingAverage {
+        /// Number of values in the window
+        window: usize,
+    },
+
+    /// Median of each value and the previous ones
+    MovingMedian {
+        /// Number of values in the window
+        window: usize,
+    },
+
+    /// Arithmetic expression of each value, such as `x / 1000`
+    Expression {
+        /// Expression
+        expression: Expression,
+    },
+}
+
+impl Transform {
+    /// Apply the transformation to a time-series
+    ///
+    /// Differences and derivatives have one value less than the time-series,
+    /// and moving windows are shorter at the start of the time-series.
+    #[must_use]
+    pub fn apply(&self, time_series: &[(DateTime<Utc>, f64)]) -> Vec<(DateTime<Utc>, f64)> {
+        match *self {
+            Self::Difference => time_series
+                .iter()
+                .tuple_windows()
+                .map(|(&(_, previous), &(instant, value))| (instant, value - previous))
+                .collect(),
+            Self::Derivative { ref unit } => {
+                let unit = unit
+                    .as_ref()
+                    .map_or(3600.0, |unit| unit.duration.as_seconds_f64());
+                time_series
+                    .iter()
+                    .tuple_windows()
+                    .filter_map(|(&(previous_instant, previous), &(instant, value))| {
+                        let elapsed = (instant - previous_instant).to_std().ok()?.as_secs_f64();
+                        (elapsed > 0.0).then(|| (instant, (value - previous) * unit / elapsed))
+                    })
+                    .collect()
+            }
+            Self::CumulativeSum => time_series
+                .iter()
+                .scan(0.0, |sum, &(instant, value)| {
+                    *sum += value;
+                    Some((instant, *sum))
+                })
+                .collect(),
+            Self::MovingAverage { window } => moving_window(time_series, window, |values| {
+                values.iter().sum::<f64>() / f64::from(u32::try_from(values.len()).unwrap_or(1))
+            }),
+            Self::MovingMedian { window } => moving_window(time_series, window, |values| {
+                let sorted: Vec<f64> = values.iter().copied().sorted_by(f64::total_cmp).collect();
+                // Both middle indices are the same for an odd number of values
+                let lower = sorted[(sorted.len() - 1) / 2];
+                let upper = sorted[sorted.len() / 2];
+                lower / 2.0 + upper / 2.0
+            }),
+            Self::Expression { ref expression } => time_series
+                .iter()
+                .map(|&(instant, value)| (instant, expression.evaluate(value)))
+                .collect(),
+        }
+    }
+
+    /// Return whether the transformation only depends on each single value
+    ///
+    /// Only such transformations can be applied to minimal and maximal values.
+    #[must_use]
+    pub fn is_pointwise(&self) -> bool {
+        matches!(*self, Self::Expression { .. })
+    }
+}
+
+/// Apply a chain of transformations to a time-series
+#[must_use]
+pub fn apply_transforms(
+    transforms: &[Transform],
+    time_series: Vec<(DateTime<Utc>, f64)>,
+) -> Vec<(DateTime<Utc>, f64)> {
+    transforms
+        .iter()
+        .fold(time_series, |time_series, transform| {
+            transform.apply(&time_series)
+        })
+}
+
+/// Apply a chain of transformations to minimal and maximal values
+///
+/// Return nothing when a transformation is not pointwise.
+#[must_use]
+pub fn apply_transforms_to_envelope(
+    transforms: &[Transform],
+    envelope: Vec<(DateTime<Utc>, f64, f64)>,
+) -> Option<Vec<(DateTime<Utc>, f64, f64)>> {
+    if !transforms.iter().all(Transform::is_pointwise) {
+        return None;
+    }
+
+    let (minima, maxima): (Vec<_>, Vec<_>) = envelope
+        .into_iter()
+        .map(|(instant, min, max)| ((instant, min), (instant, max)))
+        .unzip();
+    let envelope = apply_transforms(transforms, minima)
+        .into_iter()
+        .zip(apply_transforms(transforms, maxima))
+        .map(|((instant, min), (_, max))| (instant, min.min(max), min.max(max)))
+        .collect();
+    Some(envelope)
+}
+
+/// Compute a function over a moving window of values
+///
+/// The window ends at each value, and it contains at least one value.
+fn moving_window(
+    time_series: &[(DateTime<Utc>, f64)],
+    window: usize,
+    function: impl Fn(&VecDeque<f64>) -> f64,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let window = window.max(1);
+    let mut values = VecDeque::with_capacity(window);
+    time_series
+        .iter()
+        .map(|&(instant, value)| {
+            if values.len() == window {
+                values.pop_front();
+            }
+            values.push_back(value);
+            (instant, function(&values))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    /// A list of transformations
+    #[derive(Deserialize)]
+    struct Transforms {
+        /// Transformations
+        transforms: Vec<Transform>,
+    }
+
+    /// Parse a list of transformations
+    fn parse(text: &str) -> Option<Vec<Transform>> {
+        toml::from_str::<Transforms>(text)
+            .map(|transforms| transforms.transforms)
+            .ok()
+    }
+
+    /// Create a time-series with one value per hour
+    fn hourly(values: &[f64]) -> Option<Vec<(DateTime<Utc>, f64)>> {
+        values
+            .iter()
+            .zip(0..)
+            .map(|(&value, hour)| {
+                let instant = Utc.with_ymd_and_hms(2020, 9, 29, hour, 0, 0).single();
+                instant.map(|instant| (instant, value))
+            })
+            .collect()
+    }
+
+    /// Extract values from a time-series
+    fn values(time_series: &[(DateTime<Utc>, f64)]) -> Vec<f64> {
+        time_series.iter().map(|&(_, value)| value).collect()
+    }
+
+    #[test]
+    fn difference_and_cumulative_sum() {
+        let meter = hourly(&[10.0, 12.0, 15.0, 15.0]);
+
+        let difference = meter
+            .as_deref()
+            .map(|meter| Transform::Difference.apply(meter));
+        assert_eq!(difference.as_deref().map(values), Some(vec![2.0, 3.0, 0.0]));
+        assert_eq!(
+            difference
+                .as_deref()
+                .and_then(<[_]>::first)
+                .map(|&(instant, _)| instant),
+            meter
+                .as_deref()
+                .and_then(|meter| meter.get(1))
+                .map(|&(instant, _)| instant)
+        );
+
+        let cumulative_sum = difference
+            .as_deref()
+            .map(|difference| Transform::CumulativeSum.apply(difference));
+        assert_eq!(
+            cumulative_sum.as_deref().map(values),
+            Some(vec![2.0, 5.0, 5.0])
+        );
+    }
+
+    #[test]
+    fn derivative_per_unit() {
+        let meter = hourly(&[0.0, 60.0, 180.0]);
+
+        let per_hour = meter
+            .as_deref()
+            .map(|meter| Transform::Derivative { unit: None }.apply(meter));
+        assert_eq!(per_hour.as_deref().map(values), Some(vec![60.0, 120.0]));
+
+        let transforms = parse(r#"transforms = [{ kind = "derivative", unit = "PT1M" }]"#);
+        let per_minute = transforms
+            .zip(meter)
+            .map(|(transforms, meter)| apply_transforms(&transforms, meter));
+        assert_eq!(per_minute.as_deref().map(values), Some(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn moving_average_and_median() {
+        let noisy = hourly(&[1.0, 3.0, 2.0, 10.0, 4.0]);
+
+        let average = noisy
+            .as_deref()
+            .map(|noisy| Transform::MovingAverage { window: 2 }.apply(noisy));
+        assert_eq!(
+            average.as_deref().map(values),
+            Some(vec![1.0, 2.0, 2.5, 6.0, 7.0])
+        );
+
+        let median = noisy
+            .as_deref()
+            .map(|noisy| Transform::MovingMedian { window: 3 }.apply(noisy));
+        assert_eq!(
+            median.as_deref().map(values),
+            Some(vec![1.0, 2.0, 2.0, 3.0, 4.0])
+        );
+    }
+
+    #[test]
+    fn chained_transforms() {
+        let transforms = parse(
+            r#"transforms = [
+                { kind = "difference" },
+                { kind = "expression", expression = "x / 1000" },
+            ]"#,
+        );
+        assert_eq!(transforms.as_ref().map(Vec::len), Some(2));
+
+        let meter = hourly(&[1000.0, 3500.0, 4000.0]);
+        let transformed = transforms
+            .zip(meter)
+            .map(|(transforms, meter)| apply_transforms(&transforms, meter));
+        assert_eq!(transformed.as_deref().map(values), Some(vec![2.5, 0.5]));
+    }
+}
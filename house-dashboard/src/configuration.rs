@@ -6,8 +6,9 @@
 
 //! Data structures for parsing configuration
 
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_std::task::sleep;
 
@@ -24,6 +25,8 @@
 
 use house_dashboard_influxdb::InfluxDBClient;
 
+use crate::metrics::ChartMetrics;
+
 #[cfg(feature = "infrastructure-summary-chart")]
 use house_dashboard_infrastructure_summary::{
     process_infrastructure_summary, InfrastructureSummaryConfiguration,
@@ -35,6 +38,15 @@
 #[cfg(feature = "trend-chart")]
 use house_dashboard_trend::{process_trend, TrendConfiguration};
 
+#[cfg(feature = "single-stat-chart")]
+use house_dashboard_single_stat::{process_single_stat, SingleStatConfiguration};
+
+#[cfg(feature = "gauge-chart")]
+use house_dashboard_gauge::{process_gauge, GaugeConfiguration};
+
+#[cfg(feature = "forecast-chart")]
+use house_dashboard_forecast::{process_forecast, ForecastConfiguration};
+
 #[cfg(feature = "geographical-heatmap-chart")]
 use house_dashboard_geographical_heatmap::{
     process_geographical_heatmap, GeographicalHeatMapConfiguration,
@@ -63,6 +75,22 @@ pub struct Influxdb {
 
     /// Set to true to accept invalid TLS certificates
     pub dangerously_accept_invalid_certs: Option<bool>,
+
+    /// Number of points per chunk in InfluxDB responses
+    pub chunk_size: Option<NonZeroUsize>,
+
+    /// Configuration for writing dashboard self-metrics
+    pub self_metrics: Option<SelfMetrics>,
+}
+
+/// Self-metrics configuration
+#[derive(Debug, Deserialize)]
+pub struct SelfMetrics {
+    /// InfluxDB database
+    pub database: String,
+
+    /// InfluxDB measurement
+    pub measurement: Option<String>,
 }
 
 /// Maximum attempts for processing the chart
@@ -84,6 +112,18 @@ pub enum Chart {
     /// Chart configuration for trend
     Trend(Box<TrendConfiguration>),
 
+    #[cfg(feature = "single-stat-chart")]
+    /// Chart configuration for single-stat
+    SingleStat(Box<SingleStatConfiguration>),
+
+    #[cfg(feature = "gauge-chart")]
+    /// Chart configuration for gauge
+    Gauge(Box<GaugeConfiguration>),
+
+    #[cfg(feature = "forecast-chart")]
+    /// Chart configuration for weather forecast
+    Forecast(Box<ForecastConfiguration>),
+
     #[cfg(feature = "geographical-heatmap-chart")]
     /// Chart configuration for trend
     GeographicalHeatMap(Box<GeographicalHeatMapConfiguration>),
@@ -100,6 +140,9 @@ pub enum Chart {
 impl Chart {
     /// Process a chart
     ///
+    /// Return metrics about processing the chart together with the result,
+    /// so that metrics are available also when processing fails.
+    ///
     /// # Errors
     ///
     /// Return an error if the operation fails more than [`MAX_ATTEMPTS`] times
@@ -109,8 +152,42 @@ pub async fn process(
         influxdb_client: InfluxDBClient,
         style: &StyleConfiguration,
         index: usize,
+    ) -> (ChartMetrics, Result<(usize, Vec<u8>), Report>) {
+        let name = self
+            .name()
+            .map_or_else(|| format!("chart {index}"), ToOwned::to_owned);
+        let influxdb_client = influxdb_client.with_chart_name(&name);
+
+        let start = Instant::now();
+        let mut attempts = 0;
+        let result = self
+            .process_with_retries(&influxdb_client, style, index, &mut attempts)
+            .await;
+
+        let metrics = ChartMetrics {
+            chart: name,
+            kind: self.kind(),
+            render_time: start.elapsed(),
+            attempts,
+            success: result.is_ok(),
+            queries: influxdb_client.statistics().await,
+        };
+
+        (metrics, result)
+    }
+
+    /// Process a chart, retrying on failures
+    async fn process_with_retries(
+        &self,
+        influxdb_client: &InfluxDBClient,
+        style: &StyleConfiguration,
+        index: usize,
+        attempts: &mut u32,
     ) -> Result<(usize, Vec<u8>), Report> {
+        let mut last_error = None;
+
         for attempt in 0..MAX_ATTEMPTS {
+            *attempts = attempt + 1;
             match self
                 .process_inner(influxdb_client.clone(), style, index)
                 .await
@@ -118,6 +195,7 @@ pub async fn process(
                 ok @ Ok(_) => return ok,
                 Err(error) => {
                     warn!("Attempt {} failed: {:?}", attempt + 1, error);
+                    last_error = Some(error);
 
                     let delay = Duration::from_secs(2_u64.checked_pow(attempt + 3).unwrap_or(10));
                     info!("Trying again in {}s", delay.as_secs());
@@ -127,7 +205,76 @@ pub async fn process(
             }
         }
 
-        Err(miette!("Operation failed more than {} times", MAX_ATTEMPTS))
+        let message = format!("Operation failed more than {MAX_ATTEMPTS} times");
+        if let Some(error) = last_error {
+            Err(error.wrap_err(message))
+        } else {
+            Err(miette!(message))
+        }
+    }
+
+    /// Return the chart kind
+    fn kind(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "infrastructure-summary-chart")]
+            Self::InfrastructureSummary(_) => "InfrastructureSummary",
+
+            #[cfg(feature = "proxmox-summary-chart")]
+            Self::ProxmoxSummary(_) => "ProxmoxSummary",
+
+            #[cfg(feature = "trend-chart")]
+            Self::Trend(_) => "Trend",
+
+            #[cfg(feature = "single-stat-chart")]
+            Self::SingleStat(_) => "SingleStat",
+
+            #[cfg(feature = "gauge-chart")]
+            Self::Gauge(_) => "Gauge",
+
+            #[cfg(feature = "forecast-chart")]
+            Self::Forecast(_) => "Forecast",
+
+            #[cfg(feature = "geographical-heatmap-chart")]
+            Self::GeographicalHeatMap(_) => "GeographicalHeatMap",
+
+            #[cfg(feature = "temporal-heatmap-chart")]
+            Self::TemporalHeatMap(_) => "TemporalHeatMap",
+
+            #[cfg(feature = "image-chart")]
+            Self::Image(_) => "Image",
+        }
+    }
+
+    /// Return the chart name, if any
+    fn name(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "infrastructure-summary-chart")]
+            Self::InfrastructureSummary(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "proxmox-summary-chart")]
+            Self::ProxmoxSummary(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "trend-chart")]
+            Self::Trend(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "single-stat-chart")]
+            Self::SingleStat(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "gauge-chart")]
+            Self::Gauge(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "forecast-chart")]
+            Self::Forecast(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "geographical-heatmap-chart")]
+            Self::GeographicalHeatMap(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "temporal-heatmap-chart")]
+            Self::TemporalHeatMap(ref configuration) => Some(&configuration.title),
+
+            #[cfg(feature = "image-chart")]
+            Self::Image(_) => None,
+        }
     }
 
     /// Process a chart
@@ -163,6 +310,30 @@ async fn process_inner(
                 Ok((index, bytes))
             }
 
+            #[cfg(feature = "single-stat-chart")]
+            Self::SingleStat(ref configuration) => {
+                let bytes = process_single_stat(&influxdb_client, configuration, style, index)
+                    .await
+                    .wrap_err("cannot process single-stat chart")?;
+                Ok((index, bytes))
+            }
+
+            #[cfg(feature = "gauge-chart")]
+            Self::Gauge(ref configuration) => {
+                let bytes = process_gauge(&influxdb_client, configuration, style, index)
+                    .await
+                    .wrap_err("cannot process gauge chart")?;
+                Ok((index, bytes))
+            }
+
+            #[cfg(feature = "forecast-chart")]
+            Self::Forecast(ref configuration) => {
+                let bytes = process_forecast(&influxdb_client, configuration, style, index)
+                    .await
+                    .wrap_err("cannot process weather forecast chart")?;
+                Ok((index, bytes))
+            }
+
             #[cfg(feature = "geographical-heatmap-chart")]
             Self::GeographicalHeatMap(ref configuration) => {
                 let bytes =
@@ -26,7 +26,7 @@
 use plotters::style::{register_font, FontStyle};
 
 use futures::stream::iter as future_from_iter;
-use futures::{future::ready, stream::FuturesUnordered, StreamExt};
+use futures::{future::ready, stream::FuturesUnordered, Stream, StreamExt};
 
 use image::{ImageFormat, RgbImage};
 
@@ -50,6 +50,9 @@
 mod logging;
 use self::logging::setup as setup_logging;
 
+mod metrics;
+use self::metrics::{write_metrics, ChartMetrics};
+
 /// Main function
 ///
 /// # Errors
@@ -59,7 +62,7 @@ pub async fn main() -> Result<(), Report> {
     let arguments = parse_command_line();
     setup_logging(arguments.verbosity.try_into().into_diagnostic()?)?;
 
-    let (style_configuration, influxdb_configuration) =
+    let (style_configuration, mut influxdb_configuration) =
         parse_configuration(&arguments.configuration_directory_path)
             .await
             .wrap_err("cannot parse configuration")?;
@@ -82,9 +85,11 @@ pub async fn main() -> Result<(), Report> {
 
     trace!("Charts configurations: {:?}", charts_configurations);
 
+    let self_metrics_configuration = influxdb_configuration.self_metrics.take();
+
     let influxdb_client = create_influxdb_client(influxdb_configuration)?;
 
-    let mut tasks: FuturesUnordered<_> = charts_configurations
+    let tasks: FuturesUnordered<_> = charts_configurations
         .into_iter()
         .enumerate()
         .map(|(i, chart_configuration)| {
@@ -92,19 +97,53 @@ pub async fn main() -> Result<(), Report> {
         })
         .collect();
 
-    while let Some(result) = tasks.next().await {
-        let (index, bytes) = result?;
-        save_chart(
-            index,
-            bytes,
-            style_configuration.resolution,
-            &arguments.output_directory_path,
-        )
-        .await
-        .wrap_err("cannot save image")?;
+    let (metrics, result) = collect_charts(
+        tasks,
+        style_configuration.resolution,
+        &arguments.output_directory_path,
+    )
+    .await;
+
+    if let Some(ref self_metrics_configuration) = self_metrics_configuration {
+        write_metrics(&influxdb_client, self_metrics_configuration, &metrics).await;
     }
 
-    Ok(())
+    result
+}
+
+/// Wait for all charts and save the successful ones
+///
+/// Charts are collected even after one fails, so that all successful charts
+/// are saved and the metrics of all charts are returned.
+/// The first error is returned after all charts were collected.
+async fn collect_charts(
+    mut tasks: impl Stream<Item = (ChartMetrics, Result<(usize, Vec<u8>), Report>)> + Unpin,
+    resolution: (u32, u32),
+    output_directory_path: &Path,
+) -> (Vec<ChartMetrics>, Result<(), Report>) {
+    let mut metrics = Vec::new();
+    let mut first_error = None;
+
+    while let Some((chart_metrics, result)) = tasks.next().await {
+        metrics.push(chart_metrics);
+
+        let result = match result {
+            Ok((index, bytes)) => save_chart(index, bytes, resolution, output_directory_path)
+                .await
+                .wrap_err("cannot save image"),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            if first_error.is_none() {
+                first_error = Some(error);
+            } else {
+                warn!("{:?}", error);
+            }
+        }
+    }
+
+    (metrics, first_error.map_or(Ok(()), Err))
 }
 
 /// Parse common configuration from configuration directory
@@ -237,7 +276,12 @@ fn create_influxdb_client(
         .into_diagnostic()
         .wrap_err("Creating HTTP client")?;
 
-    let influxdb_client = InfluxDBClient::new(influxdb_configuration.url, http_client);
+    let mut influxdb_client = InfluxDBClient::new(influxdb_configuration.url, http_client);
+
+    if let Some(chunk_size) = influxdb_configuration.chunk_size {
+        debug!("Using chunk size {}", chunk_size);
+        influxdb_client = influxdb_client.with_chunk_size(chunk_size);
+    }
 
     Ok(influxdb_client)
 }
@@ -264,3 +308,58 @@ async fn save_chart(
     write_file(path, &buffer).await.into_diagnostic()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use async_std::task::block_on;
+
+    use house_dashboard_influxdb::QueryStatistics;
+
+    fn chart_metrics(chart: &str, success: bool) -> ChartMetrics {
+        ChartMetrics {
+            chart: chart.to_owned(),
+            kind: "Trend",
+            render_time: Duration::from_millis(10),
+            attempts: 1,
+            success,
+            queries: QueryStatistics::default(),
+        }
+    }
+
+    #[test]
+    fn collect_all_charts_after_failure() {
+        let output_directory_path = std::env::temp_dir().join(format!(
+            "house-dashboard-collect-charts-{}",
+            std::process::id()
+        ));
+        assert!(std::fs::create_dir_all(&output_directory_path).is_ok());
+
+        let pixel = vec![255, 0, 0];
+        let tasks = future_from_iter(vec![
+            (chart_metrics("first", true), Ok((0, pixel.clone()))),
+            (
+                chart_metrics("second", false),
+                Err(miette!("cannot fetch data")),
+            ),
+            (chart_metrics("third", true), Ok((2, pixel))),
+        ]);
+
+        let (metrics, result) = block_on(collect_charts(tasks, (1, 1), &output_directory_path));
+
+        let charts: Vec<&str> = metrics
+            .iter()
+            .map(|metrics| metrics.chart.as_str())
+            .collect();
+        assert_eq!(charts, vec!["first", "second", "third"]);
+        assert!(result.is_err_and(|error| error.to_string() == "cannot fetch data"));
+        assert!(output_directory_path.join("01.bmp").exists());
+        assert!(!output_directory_path.join("02.bmp").exists());
+        assert!(output_directory_path.join("03.bmp").exists());
+
+        assert!(std::fs::remove_dir_all(&output_directory_path).is_ok());
+    }
+}
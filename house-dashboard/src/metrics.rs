@@ -0,0 +1,111 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Data types and functions for reporting dashboard self-metrics
+
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use house_dashboard_influxdb::{InfluxDBClient, Point, QueryStatistics, Value};
+
+use crate::configuration::SelfMetrics as SelfMetricsConfiguration;
+
+/// Default measurement for self-metrics
+const DEFAULT_MEASUREMENT: &str = "house_dashboard";
+
+/// Metrics about processing a chart
+#[derive(Debug, Clone)]
+pub struct ChartMetrics {
+    /// Chart name
+    pub chart: String,
+
+    /// Chart kind
+    pub kind: &'static str,
+
+    /// Time spent processing the chart, including retries
+    pub render_time: Duration,
+
+    /// Number of attempts
+    pub attempts: u32,
+
+    /// Whether the chart was processed successfully
+    pub success: bool,
+
+    /// Statistics about the queries sent for the chart
+    pub queries: QueryStatistics,
+}
+
+impl ChartMetrics {
+    /// Convert metrics to a point
+    fn to_point(&self, measurement: &str) -> Point {
+        Point::new(measurement)
+            .with_tag("chart", &self.chart)
+            .with_tag("kind", self.kind)
+            .with_field("render_time", Value::Float(self.render_time.as_secs_f64()))
+            .with_field("attempts", Value::Integer(self.attempts.into()))
+            .with_field(
+                "retries",
+                Value::Integer(self.attempts.saturating_sub(1).into()),
+            )
+            .with_field("failure", Value::Boolean(!self.success))
+            .with_field(
+                "queries",
+                Value::Integer(saturating_i64(self.queries.queries)),
+            )
+            .with_field(
+                "query_failures",
+                Value::Integer(saturating_i64(self.queries.failures)),
+            )
+            .with_field(
+                "query_latency",
+                Value::Float(self.queries.latency.as_secs_f64()),
+            )
+            .with_field("bytes", Value::Integer(saturating_i64(self.queries.bytes)))
+    }
+}
+
+/// Write self-metrics to InfluxDB
+///
+/// Failures are only logged, since self-metrics must never prevent the
+/// dashboard from being generated.
+pub async fn write_metrics(
+    influxdb_client: &InfluxDBClient,
+    configuration: &SelfMetricsConfiguration,
+    metrics: &[ChartMetrics],
+) {
+    if metrics.is_empty() {
+        return;
+    }
+
+    let measurement = configuration
+        .measurement
+        .as_deref()
+        .unwrap_or(DEFAULT_MEASUREMENT);
+
+    let points: Vec<Point> = metrics
+        .iter()
+        .map(|metrics| metrics.to_point(measurement))
+        .collect();
+
+    debug!(
+        "Writing {} self-metrics points to database {}",
+        points.len(),
+        configuration.database
+    );
+
+    if let Err(error) = influxdb_client
+        .write(&configuration.database, &points)
+        .await
+    {
+        warn!("Cannot write self-metrics: {:?}", error);
+    }
+}
+
+/// Convert an unsigned counter to a signed integer, saturating on overflow
+fn saturating_i64(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}